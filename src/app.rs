@@ -0,0 +1,22 @@
+pub mod assets;
+pub mod build;
+pub mod check;
+pub mod config;
+pub mod detect;
+pub mod diff;
+pub mod domain;
+pub mod history;
+pub mod info;
+pub mod links;
+pub mod manage;
+pub mod manifest;
+pub mod plan;
+pub mod preview;
+pub mod publish;
+pub mod pull;
+pub mod register;
+pub mod routing;
+pub mod scan;
+pub mod serve;
+pub mod stage;
+pub mod watch;
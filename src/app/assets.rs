@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::app::links::{css_references, html_references, rename_reference, resolve, Reference};
+use crate::app::manifest::relative_key;
+use crate::utils::config::ASSET_MANIFEST_FILE;
+use crate::utils::file_filter::{build_matcher, FileFilter};
+
+/// Files that can get a hash in their name
+const FINGERPRINT_EXTENSIONS: &[&str] = &[
+    "css", "js", "mjs", "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "woff", "woff2", "ttf",
+    "otf", "eot", "wasm",
+];
+/// Files worth compressing, as they are text or otherwise uncompressed
+const COMPRESS_EXTENSIONS: &[&str] = &[
+    "html",
+    "htm",
+    "css",
+    "js",
+    "mjs",
+    "json",
+    "svg",
+    "xml",
+    "txt",
+    "wasm",
+    "webmanifest",
+];
+/// How many hex digits of the SHA-256 of a file go into its name
+const FINGERPRINT_LEN: usize = 8;
+
+/// The publish-time asset pipeline, off unless the app config turns it on
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetsConfig {
+    /// Write Brotli and gzip compressed copies of text files next to them, as `.br` and `.gz`
+    #[serde(default)]
+    pub compress: bool,
+    /// Add a hash of their contents to the names of scripts, stylesheets, images and fonts, and
+    /// point HTML and CSS at the new names. Files whose names turn up anywhere else, such as in
+    /// scripts, web manifests or `<meta>` tags, keep them.
+    #[serde(default)]
+    pub fingerprint: bool,
+    /// Files to keep the names of, in gitignore syntax
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl AssetsConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.compress || self.fingerprint
+    }
+}
+
+/// A file of the published app, as listed in `fission-assets.json`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Asset {
+    /// The path it is published at, which has its hash in it once fingerprinted
+    pub file: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub br: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gz: Option<String>,
+}
+
+/// This function runs the asset pipeline over `dir` in place, which must be a staged copy of the
+/// build directory. It fingerprints and compresses files as `config` says, then writes the
+/// manifest listing every file by its original path. Everything is derived from the contents of
+/// the files alone, so the same build always comes out the same.
+pub fn process(dir: &Path, config: &AssetsConfig) -> Result<BTreeMap<String, Asset>> {
+    // Bundlers write manifests of their own, which must not be replaced
+    let manifest_path = dir.join(ASSET_MANIFEST_FILE);
+    if manifest_path.exists() {
+        bail!(
+            "The build directory already has a {}, which the asset pipeline would overwrite",
+            ASSET_MANIFEST_FILE
+        );
+    }
+    let mut files = BTreeMap::new();
+    for entry in FileFilter::none().walk(dir)? {
+        if entry.file_type().is_file() {
+            let contents = std::fs::read(entry.path())
+                .with_context(|| format!("Failed to read {:?}", entry.path()))?;
+            files.insert(relative_key(dir, entry.path())?, contents);
+        }
+    }
+    let original = files.clone();
+
+    let renames = if config.fingerprint {
+        let excluded = build_matcher(dir, &config.exclude)?;
+        let keep = |path: &str| {
+            looks_fingerprinted(path)
+                || excluded
+                    .matched_path_or_any_parents(dir.join(path), false)
+                    .is_ignore()
+        };
+        fingerprint(&mut files, keep)
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut manifest = BTreeMap::new();
+    for (path, contents) in &files {
+        let file = renames.get(path).unwrap_or(path);
+        if file != path {
+            std::fs::remove_file(dir.join(path))?;
+        }
+        if file != path || original[path] != *contents {
+            write(&dir.join(file), contents)?;
+        }
+        let mut asset = Asset {
+            file: file.clone(),
+            size: contents.len() as u64,
+            br: None,
+            gz: None,
+        };
+        if config.compress && has_extension(file, COMPRESS_EXTENSIONS) {
+            for (suffix, compressed) in [("br", brotli(contents)?), ("gz", gzip(contents)?)] {
+                // Not worth it for small files
+                if compressed.len() >= contents.len() {
+                    continue;
+                }
+                let variant = format!("{}.{}", file, suffix);
+                write(&dir.join(&variant), &compressed)?;
+                match suffix {
+                    "br" => asset.br = Some(variant),
+                    _ => asset.gz = Some(variant),
+                }
+            }
+        }
+        manifest.insert(path.clone(), asset);
+    }
+    write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+    Ok(manifest)
+}
+
+/// Renames the files `keep` doesn't hold on to and updates the references to them, returning
+/// the new path of every renamed file. Stylesheets are renamed after whatever they point at, so
+/// their hash covers the updated references.
+fn fingerprint<F: Fn(&str) -> bool>(
+    files: &mut BTreeMap<String, Vec<u8>>,
+    keep: F,
+) -> BTreeMap<String, String> {
+    let candidates: HashSet<_> = files
+        .keys()
+        .filter(|path| has_extension(path, FINGERPRINT_EXTENSIONS) && !keep(path))
+        .cloned()
+        .collect();
+    let candidates = renamable(files, &candidates);
+    let mut renames = BTreeMap::new();
+    for path in candidates
+        .iter()
+        .filter(|path| !has_extension(path, &["css"]))
+    {
+        renames.insert(path.clone(), hashed_name(path, &files[path]));
+    }
+    let stylesheets: HashSet<_> = candidates
+        .iter()
+        .filter(|path| has_extension(path, &["css"]))
+        .cloned()
+        .collect();
+    let mut visited = HashSet::new();
+    for path in &stylesheets {
+        fingerprint_css(files, &mut renames, &stylesheets, &mut visited, path);
+    }
+    // Stylesheets that are kept still need their references updated
+    let pages: Vec<_> = files
+        .keys()
+        .filter(|path| has_extension(path, &["html", "htm", "css"]) && !renames.contains_key(*path))
+        .cloned()
+        .collect();
+    for path in pages {
+        if let Some(find) = finder(&path) {
+            rewrite(files, &renames, &path, find);
+        }
+    }
+    renames
+}
+
+/// Picks the candidates that are only ever referenced where `rewrite` updates the reference: by
+/// a URL in HTML or CSS. A file whose name is also written anywhere else, as in a script, a web
+/// manifest, `_redirects` or a `<meta>` tag, keeps it, as renaming it would break that reference.
+/// Files nothing references keep their names too, as something outside the app might.
+fn renamable(files: &BTreeMap<String, Vec<u8>>, candidates: &HashSet<String>) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+    let mut elsewhere = HashSet::new();
+    for (path, contents) in files {
+        let text = match std::str::from_utf8(contents) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let references = finder(path).map(|find| find(text)).unwrap_or_default();
+        // How often each name is written in references that get rewritten
+        let mut rewritten: HashMap<&str, usize> = HashMap::new();
+        for target in references
+            .iter()
+            .filter_map(|reference| resolve(path, &reference.value))
+        {
+            if let Some(candidate) = candidates.get(&target) {
+                *rewritten.entry(file_name(candidate)).or_default() += 1;
+                referenced.insert(target);
+            }
+        }
+        for candidate in candidates {
+            let name = file_name(candidate);
+            if text.matches(name).count() > rewritten.get(name).copied().unwrap_or(0) {
+                elsewhere.insert(candidate.clone());
+            }
+        }
+    }
+    referenced.difference(&elsewhere).cloned().collect()
+}
+
+/// The function that finds the references `rewrite` updates in the file at `path`, if any
+fn finder(path: &str) -> Option<fn(&str) -> Vec<Reference>> {
+    if has_extension(path, &["html", "htm"]) {
+        Some(html_references)
+    } else if has_extension(path, &["css"]) {
+        Some(css_references)
+    } else {
+        None
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn fingerprint_css(
+    files: &mut BTreeMap<String, Vec<u8>>,
+    renames: &mut BTreeMap<String, String>,
+    stylesheets: &HashSet<String>,
+    visited: &mut HashSet<String>,
+    path: &str,
+) {
+    // Import cycles are broken wherever they are entered
+    if !visited.insert(path.to_string()) {
+        return;
+    }
+    let imports: Vec<_> = css_references(&String::from_utf8_lossy(&files[path]))
+        .iter()
+        .filter_map(|reference| resolve(path, &reference.value))
+        .filter(|target| stylesheets.contains(target))
+        .collect();
+    for import in imports {
+        fingerprint_css(files, renames, stylesheets, visited, &import);
+    }
+    rewrite(files, renames, path, css_references);
+    renames.insert(path.to_string(), hashed_name(path, &files[path]));
+}
+
+/// Points the references of the file at `path` at the new names of renamed files. Files that
+/// aren't UTF-8 are left alone.
+fn rewrite(
+    files: &mut BTreeMap<String, Vec<u8>>,
+    renames: &BTreeMap<String, String>,
+    path: &str,
+    find: fn(&str) -> Vec<Reference>,
+) {
+    let contents = match std::str::from_utf8(&files[path]) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+    let mut rewritten = String::with_capacity(contents.len());
+    let mut last = 0;
+    for reference in find(contents) {
+        let new_path = match resolve(path, &reference.value).and_then(|target| renames.get(&target))
+        {
+            Some(new_path) => new_path,
+            None => continue,
+        };
+        let new_name = file_name(new_path);
+        rewritten.push_str(&contents[last..reference.start]);
+        rewritten.push_str(&rename_reference(&reference.value, new_name));
+        last = reference.end;
+    }
+    rewritten.push_str(&contents[last..]);
+    files.insert(path.to_string(), rewritten.into_bytes());
+}
+
+/// This function returns the path with a hash of the contents before its extension, as in
+/// `assets/app.3f2a9c1b.css`
+pub fn hashed_name(path: &str, contents: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(contents));
+    let hash = &hash[..FINGERPRINT_LEN];
+    match path.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => {
+            format!("{}.{}.{}", stem, hash, extension)
+        }
+        _ => format!("{}.{}", path, hash),
+    }
+}
+
+/// This function returns whether a bundler already put a hash in the file's name, as in
+/// `index-4f3a2b1c.js` or `main.8f3e1a2b.chunk.js`
+pub fn looks_fingerprinted(path: &str) -> bool {
+    let name = file_name(path);
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    stem.split(['.', '-', '_']).any(|part| {
+        (8..=32).contains(&part.len())
+            && part.chars().all(|c| c.is_ascii_alphanumeric())
+            && part.chars().any(|c| c.is_ascii_digit())
+    })
+}
+
+fn has_extension(path: &str, extensions: &[&str]) -> bool {
+    match path.rsplit_once('.') {
+        Some((_, extension)) => extensions.contains(&extension.to_ascii_lowercase().as_str()),
+        None => false,
+    }
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<()> {
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn brotli(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 11, 22);
+        writer.write_all(contents)?;
+    }
+    Ok(out)
+}
+
+fn gzip(contents: &[u8]) -> Result<Vec<u8>> {
+    // The header holds no file name or time, so the output only depends on the contents
+    let mut encoder = GzEncoder::new(vec![], Compression::best());
+    encoder.write_all(contents)?;
+    Ok(encoder.finish()?)
+}
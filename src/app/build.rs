@@ -0,0 +1,45 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use crate::app::config::BuildConfig;
+
+/// This function runs the app's build command, if it has one, in `app_dir`. Its output goes
+/// straight to the terminal, and a failing build fails the publish so stale files never go out.
+pub fn run_build(app_dir: &Path, build: &BuildConfig) -> Result<()> {
+    let command = match &build.command {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    println!("🔨 Running `{}`...", command);
+    let status = shell(command)
+        .current_dir(app_dir)
+        .envs(&build.env)
+        .status()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+    if !status.success() {
+        bail!(
+            "The build command `{}` failed ({}). Fix the build or pass --skip-build.",
+            command,
+            status
+        );
+    }
+    println!("{}", "✅ Built".bright_green());
+    Ok(())
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::app::links::{css_references, html_references, resolve};
+use crate::app::manifest::relative_key;
+use crate::app::routing::Routing;
+use crate::utils::file_filter::FileFilter;
+
+/// A reference to a file that won't be published
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// The path of the referencing file, relative to the build directory
+    pub path: String,
+    /// The 1-based line the reference is on
+    pub line: usize,
+    /// The URL as it is written in the file
+    pub reference: String,
+}
+
+impl BrokenLink {
+    pub fn print(&self) {
+        println!("  {}:{} {}", self.path, self.line, self.reference.red());
+    }
+}
+
+/// This function checks that every relative link, script, stylesheet and image in the HTML and
+/// CSS files of `build_dir` points at a file the filter lets through. References the `routing`
+/// rules rewrite or redirect count as fine, as the gateway answers them, unless the rule answers
+/// with a 404.
+pub fn check_links(
+    build_dir: &Path,
+    filter: &FileFilter,
+    routing: &Routing,
+) -> Result<Vec<BrokenLink>> {
+    let mut files = BTreeSet::new();
+    let mut dirs = BTreeSet::new();
+    for entry in filter.walk(build_dir)? {
+        let key = relative_key(build_dir, entry.path())?;
+        if entry.file_type().is_dir() {
+            dirs.insert(key);
+        } else {
+            files.insert(key);
+        }
+    }
+
+    let mut broken = vec![];
+    for path in &files {
+        let find = match path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+        {
+            Some(ext) if ext == "html" || ext == "htm" => html_references,
+            Some(ext) if ext == "css" => css_references,
+            _ => continue,
+        };
+        // Files that aren't text have nothing to check
+        let contents = match std::fs::read_to_string(build_dir.join(path)) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        for reference in find(&contents) {
+            let target = match resolve(path, &reference.value) {
+                Some(target) => target,
+                None => continue,
+            };
+            if !is_served(&target, &files, &dirs, routing) {
+                broken.push(BrokenLink {
+                    path: path.clone(),
+                    line: reference.line,
+                    reference: reference.value,
+                });
+            }
+        }
+    }
+    Ok(broken)
+}
+
+/// Whether the gateway has something other than a 404 for `target`, a path as `resolve` returns
+fn is_served(
+    target: &str,
+    files: &BTreeSet<String>,
+    dirs: &BTreeSet<String>,
+    routing: &Routing,
+) -> bool {
+    if exists(target, files, dirs) {
+        return true;
+    }
+    let url = format!("/{}", target);
+    routing.redirects.iter().any(|rule| {
+        let to = match rule.apply(&url) {
+            Some(to) if rule.status != 404 => to,
+            _ => return false,
+        };
+        if rule.is_redirect() {
+            return true;
+        }
+        // A rewrite only helps if what it serves is there, and a page in place of a script or
+        // an image, as single-page apps fall back to, is still broken
+        let to = to.trim_start_matches('/');
+        exists(to, files, dirs) && (is_asset(to) || !is_asset(target))
+    })
+}
+
+fn exists(target: &str, files: &BTreeSet<String>, dirs: &BTreeSet<String>) -> bool {
+    let dir = target.trim_end_matches('/');
+    if files.contains(target) && !target.ends_with('/') {
+        return true;
+    }
+    (dir.is_empty() || dirs.contains(dir)) && files.contains(&index_of(dir))
+}
+
+/// Whether the path is of a file other than a page, such as `app.js`
+fn is_asset(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((_, extension)) => !["html", "htm"].contains(&extension.to_ascii_lowercase().as_str()),
+        None => false,
+    }
+}
+
+fn index_of(dir: &str) -> String {
+    if dir.is_empty() {
+        "index.html".to_string()
+    } else {
+        format!("{}/index.html", dir)
+    }
+}
+
+/// This function prints the broken references, or that there are none
+pub fn print(broken: &[BrokenLink]) {
+    if broken.is_empty() {
+        println!("{}", "✅ No broken links".bright_green());
+        return;
+    }
+    println!(
+        "{}",
+        "⚠️  These references point at missing files:".yellow()
+    );
+    for link in broken {
+        link.print();
+    }
+}
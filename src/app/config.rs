@@ -0,0 +1,849 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use ignore::gitignore::GitignoreBuilder;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+
+use crate::app::assets::AssetsConfig;
+use crate::app::routing::{check_header_name, check_pattern, RoutingConfig};
+use crate::utils::config::{
+    APP_CONFIG_FILE, APP_CONFIG_TOML_FILE, APP_CONFIG_VERSION, PREVIEW_TTL_DAYS,
+};
+
+/// The per-app config (`fission.yaml` or `fission.toml`) that `fission app register` writes to
+/// the app directory
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// The version of the config format, missing from configs written by the legacy CLI
+    #[serde(default)]
+    pub version: u32,
+    /// The app's URL, such as `my-app.fission.app`
+    pub url: String,
+    #[serde(default)]
+    pub build: BuildConfig,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Files to publish even though they look sensitive, in gitignore syntax
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_sensitive: Vec<String>,
+    /// Other deployments of the app, such as `staging`, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, Environment>,
+    #[serde(default, skip_serializing_if = "PreviewConfig::is_default")]
+    pub previews: PreviewConfig,
+    #[serde(default, skip_serializing_if = "RoutingConfig::is_default")]
+    pub routing: RoutingConfig,
+    #[serde(default, skip_serializing_if = "AssetsConfig::is_default")]
+    pub assets: AssetsConfig,
+}
+
+/// Where and how a named environment is published, in place of the app's own settings
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    pub url: String,
+    /// Replaces the build directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Replaces the build command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Added to the build environment, replacing variables with the same name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+/// How preview deployments (`fission app publish --preview <branch>`) are handled
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviewConfig {
+    /// How many days after its last publish `fission app previews prune` deletes a preview
+    #[serde(default = "default_preview_ttl")]
+    pub ttl_days: u64,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            ttl_days: PREVIEW_TTL_DAYS,
+        }
+    }
+}
+
+impl PreviewConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn default_preview_ttl() -> u64 {
+    PREVIEW_TTL_DAYS
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+/// A problem with the app config, pointing at the line it is on when that is known
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// The 1-based line number
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The keys a table of the config can have, to point out the ones that aren't settings
+enum Shape {
+    Table(&'static [(&'static str, Shape)]),
+    /// A table keyed by name, such as `environments`
+    Named(&'static Shape),
+    List(&'static Shape),
+    /// A value whose keys are not settings, such as environment variables
+    Any,
+}
+
+const ENVIRONMENT: Shape = Shape::Table(&[
+    ("url", Shape::Any),
+    ("dir", Shape::Any),
+    ("command", Shape::Any),
+    ("env", Shape::Any),
+]);
+
+const REDIRECT: Shape = Shape::Table(&[
+    ("from", Shape::Any),
+    ("to", Shape::Any),
+    ("status", Shape::Any),
+]);
+
+const APP_CONFIG: Shape = Shape::Table(&[
+    ("version", Shape::Any),
+    ("url", Shape::Any),
+    (
+        "build",
+        Shape::Table(&[
+            ("dir", Shape::Any),
+            ("command", Shape::Any),
+            ("env", Shape::Any),
+        ]),
+    ),
+    ("ignore", Shape::Any),
+    ("allow_sensitive", Shape::Any),
+    ("environments", Shape::Named(&ENVIRONMENT)),
+    ("previews", Shape::Table(&[("ttl_days", Shape::Any)])),
+    (
+        "routing",
+        Shape::Table(&[
+            ("spa", Shape::Any),
+            ("redirects", Shape::List(&REDIRECT)),
+            ("headers", Shape::Any),
+        ]),
+    ),
+    (
+        "assets",
+        Shape::Table(&[
+            ("compress", Shape::Any),
+            ("fingerprint", Shape::Any),
+            ("exclude", Shape::Any),
+        ]),
+    ),
+]);
+
+impl AppConfig {
+    /// Finds the app config in `app_dir`, if there is one
+    pub fn find(app_dir: &Path) -> Result<Option<(PathBuf, ConfigFormat)>> {
+        let yaml = app_dir.join(APP_CONFIG_FILE);
+        let toml = app_dir.join(APP_CONFIG_TOML_FILE);
+        match (yaml.exists(), toml.exists()) {
+            (true, true) => bail!(
+                "Both {} and {} are in {:?}, remove one of them",
+                APP_CONFIG_FILE,
+                APP_CONFIG_TOML_FILE,
+                app_dir
+            ),
+            (true, false) => Ok(Some((yaml, ConfigFormat::Yaml))),
+            (false, true) => Ok(Some((toml, ConfigFormat::Toml))),
+            (false, false) => Ok(None),
+        }
+    }
+
+    /// Reads, validates and migrates the app config in `app_dir`
+    pub fn load(app_dir: &Path) -> Result<Self> {
+        let (mut config, _) = Self::read(app_dir)?;
+        config.migrate();
+        Ok(config)
+    }
+
+    /// Reads and validates the app config in `app_dir` as it is written, returning it along with
+    /// its path. Configs newer than this CLI only get a warning about settings it doesn't know.
+    pub fn read(app_dir: &Path) -> Result<(Self, PathBuf)> {
+        let (path, format) = match Self::find(app_dir)? {
+            Some(x) => x,
+            None => bail!(
+                "No {} found in {:?}. Run `fission app register` to set up the app.",
+                APP_CONFIG_FILE,
+                app_dir
+            ),
+        };
+        let contents =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let config = Self::parse(&contents, format)
+            .map_err(|issue| anyhow!("Invalid app config {:?}, {}", path, issue))?;
+        let mut issues = config.validate(&contents, format);
+        let unknown = Self::unknown_keys(&contents, format);
+        if config.version > APP_CONFIG_VERSION {
+            eprintln!(
+                "{} {:?} is version {}, newer than this CLI understands. Settings it doesn't know \
+                 about are ignored.",
+                "⚠️ ".yellow(),
+                path,
+                config.version
+            );
+            for issue in &unknown {
+                eprintln!("  {}", issue);
+            }
+        } else {
+            issues.extend(unknown);
+        }
+        if !issues.is_empty() {
+            let issues: Vec<_> = issues.iter().map(|issue| format!("  {}", issue)).collect();
+            bail!("Invalid app config {:?}:\n{}", path, issues.join("\n"));
+        }
+        Ok((config, path))
+    }
+
+    /// This function parses an app config, pointing at the line of the first syntax or type
+    /// error if there is one
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self, Issue> {
+        match format {
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| Issue {
+                line: e.location().map(|location| location.line()),
+                message: strip_location(&e.to_string()),
+            }),
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| Issue {
+                line: e.line_col().map(|(line, _)| line + 1),
+                message: strip_location(&e.to_string()),
+            }),
+        }
+    }
+
+    /// This function lists the keys of an app config that aren't settings, such as misspelled
+    /// ones, which parsing silently ignores. Configs newer than this CLI can have settings it
+    /// doesn't know about, so callers only treat these as errors up to `APP_CONFIG_VERSION`.
+    pub fn unknown_keys(contents: &str, format: ConfigFormat) -> Vec<Issue> {
+        let value = match format {
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_json::Value>(contents).ok(),
+            ConfigFormat::Toml => toml::from_str::<serde_json::Value>(contents).ok(),
+        };
+        let mut issues = vec![];
+        if let Some(value) = value {
+            unknown_keys_in(
+                &value,
+                &APP_CONFIG,
+                &mut vec![],
+                contents,
+                format,
+                &mut issues,
+            );
+        }
+        issues
+    }
+
+    /// This method checks the values of the config. `contents` is the text it was parsed from,
+    /// used to find the line each problem is on.
+    pub fn validate(&self, contents: &str, format: ConfigFormat) -> Vec<Issue> {
+        let line = |path: &[&str]| line_of(contents, format, path);
+        let mut issues = vec![];
+        let mut issue = |line: Option<usize>, message: String| issues.push(Issue { line, message });
+
+        if let Err(e) = check_host(&self.url) {
+            issue(line(&["url"]), e);
+        }
+        if self.build.dir.as_os_str().is_empty() {
+            issue(
+                line(&["build", "dir"]).or_else(|| line(&["build"])),
+                "The build directory can't be empty".to_string(),
+            );
+        }
+        if let Some(command) = &self.build.command {
+            if command.trim().is_empty() {
+                issue(
+                    line(&["build", "command"]),
+                    "The build command can't be empty".to_string(),
+                );
+            }
+        }
+        let env_names = self
+            .build
+            .env
+            .keys()
+            .map(|name| (vec!["build", "env", name], name))
+            .chain(self.environments.iter().flat_map(|(env_name, env)| {
+                env.env
+                    .keys()
+                    .map(move |name| (vec!["environments", env_name, "env", name], name))
+            }));
+        for (path, name) in env_names {
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                issue(
+                    line(&path),
+                    format!("{:?} is not a valid environment variable name", name),
+                );
+            }
+        }
+        for (name, env) in &self.environments {
+            if let Err(e) = check_host(&env.url) {
+                issue(
+                    line(&["environments", name, "url"]),
+                    format!("In environment {:?}: {}", name, e),
+                );
+            }
+        }
+        if self.previews.ttl_days == 0 {
+            issue(
+                line(&["previews", "ttl_days"]),
+                "Previews must live for at least a day".to_string(),
+            );
+        }
+        for (i, rule) in self.routing.redirects.iter().enumerate() {
+            if let Err(e) = rule.check() {
+                issue(
+                    line(&["routing", "redirects", &i.to_string(), "from"]),
+                    format!("Invalid redirect {}: {}", rule, e),
+                );
+            }
+        }
+        for (path, headers) in &self.routing.headers {
+            if let Err(e) = check_pattern(path) {
+                issue(
+                    line(&["routing", "headers", path]),
+                    format!("Invalid headers for {}: {}", path, e),
+                );
+            }
+            for name in headers.keys() {
+                if let Err(e) = check_header_name(name) {
+                    issue(
+                        line(&["routing", "headers", path, name]),
+                        format!("Invalid headers for {}: {}", path, e),
+                    );
+                }
+            }
+        }
+        for (key, patterns) in [
+            (&["ignore"][..], &self.ignore),
+            (&["allow_sensitive"], &self.allow_sensitive),
+            (&["assets", "exclude"], &self.assets.exclude),
+        ] {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if let Err(e) = GitignoreBuilder::new("").add_line(None, pattern) {
+                    let i = i.to_string();
+                    let path: Vec<&str> = key.iter().copied().chain([i.as_str()]).collect();
+                    issue(
+                        line(&path),
+                        format!(
+                            "Invalid {} pattern {:?}: {}",
+                            key[key.len() - 1],
+                            pattern,
+                            e
+                        ),
+                    );
+                }
+            }
+        }
+        issues
+    }
+
+    /// This method returns the config as it applies to the named environment
+    pub fn for_env(&self, name: &str) -> Result<Self> {
+        let env = match self.environments.get(name) {
+            Some(env) => env,
+            None => {
+                let known: Vec<_> = self.environments.keys().map(String::as_str).collect();
+                bail!(
+                    "There is no environment named {:?}, the app config defines {}",
+                    name,
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                )
+            }
+        };
+        let mut config = self.clone();
+        config.url = env.url.clone();
+        if let Some(dir) = &env.dir {
+            config.build.dir = dir.clone();
+        }
+        if let Some(command) = &env.command {
+            config.build.command = Some(command.clone());
+        }
+        config
+            .build
+            .env
+            .extend(env.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(config)
+    }
+
+    /// This method upgrades the config to the current version, returning whether anything
+    /// changed. Configs newer than the current version are left as they are.
+    ///
+    /// The legacy CLI's configs have no version field and read as version 0. Version 1 only
+    /// added the field, and version 2 added `environments`, `previews`, `routing` and `assets`,
+    /// which older CLIs would publish without. Every key of an older config still means the same,
+    /// so upgrading only changes the version.
+    pub fn migrate(&mut self) -> bool {
+        if self.version >= APP_CONFIG_VERSION {
+            return false;
+        }
+        self.version = APP_CONFIG_VERSION;
+        true
+    }
+
+    /// Writes the app config to `app_dir`, in the format of the config already there or as YAML
+    pub fn save(&self, app_dir: &Path) -> Result<()> {
+        let (path, format) = Self::find(app_dir)?
+            .unwrap_or_else(|| (app_dir.join(APP_CONFIG_FILE), ConfigFormat::Yaml));
+        let contents = match format {
+            ConfigFormat::Yaml => serde_yaml::to_string(self)?,
+            // Going through a value puts tables after plain values, as TOML requires
+            ConfigFormat::Toml => toml::to_string(&toml::Value::try_from(self)?)?,
+        };
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+/// This function checks the app config in `app_dir`, failing with every problem it has if there
+/// are any. With `migrate`, a config written for an older version is rewritten in the
+/// current format.
+pub fn check(app_dir: &Path, migrate: bool) -> Result<()> {
+    let (mut config, path) = AppConfig::read(app_dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let build_dir = app_dir.join(&config.build.dir);
+    if config.build.command.is_none() && !build_dir.is_dir() {
+        println!(
+            "  {} the build directory {:?} does not exist yet",
+            "⚠️ ".yellow(),
+            build_dir
+        );
+    }
+
+    match config.version {
+        v if v < APP_CONFIG_VERSION && migrate => {
+            config.migrate();
+            config.save(app_dir)?;
+            println!(
+                "  Migrated {} from version {} to {}",
+                name, v, config.version
+            );
+        }
+        v if v < APP_CONFIG_VERSION => println!(
+            "  {} is version {}, pass --migrate to upgrade it to version {}",
+            name, v, APP_CONFIG_VERSION
+        ),
+        _ => {}
+    }
+    println!("{} {} is valid", "✅".bright_green(), name);
+    Ok(())
+}
+
+fn check_host(url: &str) -> Result<(), String> {
+    let valid = url.contains('.')
+        && url.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "{:?} is not a valid app URL, expected a domain name such as my-app.fission.app",
+            url
+        ))
+    }
+}
+
+fn unknown_keys_in(
+    value: &serde_json::Value,
+    shape: &Shape,
+    path: &mut Vec<String>,
+    contents: &str,
+    format: ConfigFormat,
+    issues: &mut Vec<Issue>,
+) {
+    match (shape, value) {
+        (Shape::Table(keys), serde_json::Value::Object(table)) => {
+            for (key, value) in table {
+                path.push(key.clone());
+                match keys.iter().find(|(known, _)| *known == key.as_str()) {
+                    Some((_, shape)) => {
+                        unknown_keys_in(value, shape, path, contents, format, issues)
+                    }
+                    None => {
+                        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+                        // List indices are only there to find the line
+                        let name: Vec<&str> = path
+                            .iter()
+                            .copied()
+                            .filter(|segment| segment.parse::<usize>().is_err())
+                            .collect();
+                        issues.push(Issue {
+                            line: line_of(contents, format, &path),
+                            message: format!("Unknown setting `{}`", name.join(".")),
+                        })
+                    }
+                }
+                path.pop();
+            }
+        }
+        (Shape::Named(shape), serde_json::Value::Object(table)) => {
+            for (key, value) in table {
+                path.push(key.clone());
+                unknown_keys_in(value, shape, path, contents, format, issues);
+                path.pop();
+            }
+        }
+        (Shape::List(shape), serde_json::Value::Array(values)) => {
+            for (i, value) in values.iter().enumerate() {
+                path.push(i.to_string());
+                unknown_keys_in(value, shape, path, contents, format, issues);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Serde errors end with the position, which `Issue` shows separately
+fn strip_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// This function finds the line of the setting at `path`, such as `["build", "command"]`, with
+/// list items given by their index. It deserializes the config again, stopping at the setting,
+/// so the position comes from the YAML or TOML parser rather than a guess.
+fn line_of(contents: &str, format: ConfigFormat, path: &[&str]) -> Option<usize> {
+    let locator = Locator { path, format };
+    match format {
+        ConfigFormat::Yaml => match locator
+            .deserialize(serde_yaml::Deserializer::from_str(contents))
+        {
+            Err(e) if e.to_string().contains(FOUND) => e.location().map(|location| location.line()),
+            _ => None,
+        },
+        ConfigFormat::Toml => {
+            let mut deserializer = toml::Deserializer::new(contents);
+            match locator.deserialize(&mut deserializer) {
+                // Tables don't have a span of their own
+                Ok(Some(offset)) if offset > 0 => {
+                    Some(contents[..offset].matches('\n').count() + 1)
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// The message `Locator` fails with once it reaches the setting it looks for
+const FOUND: &str = "found the setting";
+
+/// Walks a config down `path`. For YAML it fails at the key of the setting, as errors carry the
+/// position of the event they were raised at. For TOML it returns the byte offset of the value,
+/// which `toml::Spanned` records.
+struct Locator<'a> {
+    path: &'a [&'a str],
+    format: ConfigFormat,
+}
+
+impl<'de> DeserializeSeed<'de> for Locator<'_> {
+    type Value = Option<usize>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match (self.path.is_empty(), self.format) {
+            (true, ConfigFormat::Toml) => {
+                let value = toml::Spanned::<IgnoredAny>::deserialize(deserializer)?;
+                Ok(Some(value.start()))
+            }
+            (true, ConfigFormat::Yaml) => deserializer.deserialize_any(Found),
+            (false, _) => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Locator<'_> {
+    type Value = Option<usize>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an app config")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (name, rest) = match self.path.split_first() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let key = KeySeed {
+            name,
+            fail: rest.is_empty() && self.format == ConfigFormat::Yaml,
+        };
+        while let Some(found) = map.next_key_seed(key)? {
+            if found {
+                return map.next_value_seed(Locator {
+                    path: rest,
+                    format: self.format,
+                });
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(None)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let (index, rest) = match self.path.split_first() {
+            Some((index, rest)) => match index.parse::<usize>() {
+                Ok(index) => (index, rest),
+                Err(_) => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        for _ in 0..index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(None);
+            }
+        }
+        let locator = Locator {
+            path: rest,
+            format: self.format,
+        };
+        Ok(seq.next_element_seed(locator)?.flatten())
+    }
+
+    // Anything else is a value the path can't go into
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+}
+
+/// Reads a key, telling whether it is `name`. With `fail`, it fails on `name` instead.
+#[derive(Clone, Copy)]
+struct KeySeed<'a> {
+    name: &'a str,
+    fail: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for KeySeed<'_> {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a key")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<bool, E> {
+        if key == self.name && self.fail {
+            return Err(E::custom(FOUND));
+        }
+        Ok(key == self.name)
+    }
+
+    fn visit_bool<E: de::Error>(self, key: bool) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, key: i64) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, key: u64) -> Result<bool, E> {
+        self.visit_str(&key.to_string())
+    }
+}
+
+/// Fails once the value it is handed has been read, so the error points at it
+struct Found;
+
+impl<'de> Visitor<'de> for Found {
+    type Value = Option<usize>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a setting")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Err(E::custom(FOUND))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, _: A) -> Result<Self::Value, A::Error> {
+        Err(de::Error::custom(FOUND))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, _: A) -> Result<Self::Value, A::Error> {
+        Err(de::Error::custom(FOUND))
+    }
+}
+
+/// How the app is built. Configs without a build command can give the directory on its own, as
+/// in `build: ./dist`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(into = "RawBuildConfig")]
+pub struct BuildConfig {
+    /// The directory with the files to publish, relative to the app directory
+    pub dir: PathBuf,
+    /// A shell command run in the app directory before publishing, such as `npm run build`
+    pub command: Option<String>,
+    /// Environment variables set for the build command
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            command: None,
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+impl BuildConfig {
+    /// This method describes a build directory without a build command
+    pub fn dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// The table form of `build`, which errors point into like any other table
+#[derive(Deserialize)]
+struct FullBuildConfig {
+    dir: PathBuf,
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+impl<'de> Deserialize<'de> for BuildConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BuildConfigVisitor)
+    }
+}
+
+struct BuildConfigVisitor;
+
+impl<'de> Visitor<'de> for BuildConfigVisitor {
+    type Value = BuildConfig;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a build directory such as `./dist`, or a table with `dir` and optionally `command` \
+             and `env`"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, dir: &str) -> Result<BuildConfig, E> {
+        Ok(BuildConfig::dir(dir))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<BuildConfig, A::Error> {
+        let FullBuildConfig { dir, command, env } =
+            FullBuildConfig::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        Ok(BuildConfig { dir, command, env })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RawBuildConfig {
+    Dir(PathBuf),
+    Full {
+        dir: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        env: BTreeMap<String, String>,
+    },
+}
+
+impl From<BuildConfig> for RawBuildConfig {
+    fn from(build: BuildConfig) -> Self {
+        // Keep the short form the legacy CLI understands whenever there is nothing else to say
+        if build.command.is_none() && build.env.is_empty() {
+            return Self::Dir(build.dir);
+        }
+        Self::Full {
+            dir: build.dir,
+            command: build.command,
+            env: build.env,
+        }
+    }
+}
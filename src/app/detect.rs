@@ -0,0 +1,114 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// A frontend setup `fission app register` knows the build directory of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framework {
+    SvelteKit,
+    Next,
+    CreateReactApp,
+    Vite,
+    Elm,
+    PlainHtml,
+}
+
+impl fmt::Display for Framework {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Framework::SvelteKit => "SvelteKit (static adapter)",
+            Framework::Next => "Next.js (static export)",
+            Framework::CreateReactApp => "Create React App",
+            Framework::Vite => "Vite",
+            Framework::Elm => "Elm",
+            Framework::PlainHtml => "plain HTML",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What was found in the app directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detected {
+    pub framework: Framework,
+    /// The build directory, relative to the app directory
+    pub build_dir: PathBuf,
+    pub build_command: Option<String>,
+}
+
+/// This function looks at the files in `app_dir` to work out how the app is built and where the
+/// output ends up. Frameworks are checked from the most to the least specific, since SvelteKit
+/// for instance is built with Vite.
+pub fn detect(app_dir: &Path) -> Option<Detected> {
+    if let Some(package) = read_package_json(app_dir) {
+        let framework = if has_dependency(&package, "@sveltejs/adapter-static") {
+            Some((Framework::SvelteKit, "build"))
+        } else if has_dependency(&package, "@sveltejs/kit") {
+            // Only the static adapter produces files that can be published as they are
+            None
+        } else if has_dependency(&package, "next") {
+            Some((Framework::Next, "out"))
+        } else if has_dependency(&package, "react-scripts") {
+            Some((Framework::CreateReactApp, "build"))
+        } else if has_dependency(&package, "vite") {
+            Some((Framework::Vite, "dist"))
+        } else {
+            None
+        };
+        if let Some((framework, build_dir)) = framework {
+            let build_command = package
+                .pointer("/scripts/build")
+                .map(|_| format!("{} run build", package_manager(app_dir)));
+            return Some(Detected {
+                framework,
+                build_dir: PathBuf::from(build_dir),
+                build_command,
+            });
+        }
+    }
+
+    if app_dir.join("elm.json").is_file() {
+        let build_command = if app_dir.join("src/Main.elm").is_file() {
+            Some("elm make src/Main.elm --optimize --output=public/elm.js".to_string())
+        } else {
+            None
+        };
+        return Some(Detected {
+            framework: Framework::Elm,
+            build_dir: PathBuf::from("public"),
+            build_command,
+        });
+    }
+
+    ["public", "."]
+        .iter()
+        .find(|dir| app_dir.join(dir).join("index.html").is_file())
+        .map(|dir| Detected {
+            framework: Framework::PlainHtml,
+            build_dir: PathBuf::from(dir),
+            build_command: None,
+        })
+}
+
+fn read_package_json(app_dir: &Path) -> Option<Value> {
+    let contents = std::fs::read(app_dir.join("package.json")).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+fn has_dependency(package: &Value, name: &str) -> bool {
+    ["dependencies", "devDependencies"]
+        .iter()
+        .any(|key| package[key].get(name).is_some())
+}
+
+/// Picks the package manager whose lockfile is in the app directory
+fn package_manager(app_dir: &Path) -> &'static str {
+    if app_dir.join("pnpm-lock.yaml").is_file() {
+        "pnpm"
+    } else if app_dir.join("yarn.lock").is_file() {
+        "yarn"
+    } else {
+        "npm"
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::config::AppConfig;
+use crate::app::manifest::relative_key;
+use crate::app::publish::{build_dir, file_filter, root_cid};
+use crate::app::stage::stage_in;
+use crate::ipfs::Ipfs;
+use crate::server::FissionServer;
+use crate::utils::file_filter::FileFilter;
+use crate::utils::file_management::format_size;
+
+/// A file in the build directory or in the published app
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    pub cid: String,
+    pub size: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileChange {
+    /// The path relative to the build directory, always separated by `/`
+    pub path: String,
+    pub kind: ChangeKind,
+    /// The published size, `None` for added files
+    pub old_size: Option<u64>,
+    /// The local size, `None` for removed files
+    pub new_size: Option<u64>,
+}
+
+impl FileChange {
+    /// This method formats the path with the size, or how much the size changed
+    pub fn describe(&self) -> String {
+        match (self.old_size, self.new_size) {
+            (Some(old), Some(new)) => {
+                let delta = if new >= old {
+                    format!("+{}", format_size(new - old))
+                } else {
+                    format!("-{}", format_size(old - new))
+                };
+                format!(
+                    "{} ({} → {}, {})",
+                    self.path,
+                    format_size(old),
+                    format_size(new),
+                    delta
+                )
+            }
+            (Some(size), None) | (None, Some(size)) => {
+                format!("{} ({})", self.path, format_size(size))
+            }
+            (None, None) => self.path.clone(),
+        }
+    }
+
+    pub fn print(&self) {
+        let line = self.describe();
+        match self.kind {
+            ChangeKind::Added => println!("  {} {}", "+".green(), line),
+            ChangeKind::Removed => println!("  {} {}", "-".red(), line),
+            ChangeKind::Changed => println!("  {} {}", "~".yellow(), line),
+        }
+    }
+}
+
+/// The build directory of an app next to what is currently published
+pub struct AppDiff {
+    pub url: String,
+    /// The CID the build directory would be published as
+    pub cid: String,
+    /// The CID the app currently points to, if it was published before
+    pub current_cid: Option<String>,
+    /// Every file in the build directory by its path relative to it
+    pub files: BTreeMap<String, FileInfo>,
+}
+
+impl AppDiff {
+    /// This method hashes the build directory of the app in `app_dir`, without writing anything
+    /// to IPFS, and asks the server which CID the app at `config.url` points to. Nothing is
+    /// written to `app_dir` either: if the files need staging, that happens in a scratch
+    /// directory which is removed again.
+    pub fn compute<I, S>(
+        ipfs: &I,
+        server: &S,
+        app_dir: &Path,
+        config: &AppConfig,
+        include_hidden: bool,
+    ) -> Result<Self>
+    where
+        I: Ipfs + Sync,
+        S: FissionServer + Sync,
+    {
+        let filter = file_filter(config, include_hidden);
+        let scratch = scratch_dir();
+        let hashed = stage_in(&scratch, config, &build_dir(app_dir, config)?, &filter).and_then(
+            |build_dir| {
+                let hashes = block_on(ipfs.hash(&build_dir, &filter))?;
+                let cid = root_cid(&build_dir, &hashes)?;
+                Ok((cid, local_files(&build_dir, &filter, &hashes)?))
+            },
+        );
+        if scratch.exists() {
+            std::fs::remove_dir_all(&scratch)
+                .with_context(|| format!("Failed to remove {:?}", scratch))?;
+        }
+        let (cid, files) = hashed?;
+        let current_cid = block_on(server.app_cid(&config.url))?;
+        Ok(Self {
+            url: config.url.clone(),
+            cid,
+            current_cid,
+            files,
+        })
+    }
+
+    /// This method lists the files that differ from the published app. Listing the published
+    /// files may fetch them from the swarm.
+    pub fn changes<I: Ipfs + Sync>(&self, ipfs: &I) -> Result<Vec<FileChange>> {
+        match &self.current_cid {
+            None => Ok(diff_files(&self.files, &BTreeMap::new())),
+            Some(current) if *current == self.cid => Ok(vec![]),
+            Some(current) => Ok(diff_files(&self.files, &published_files(ipfs, current)?)),
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.values().map(|file| file.size).sum()
+    }
+}
+
+/// This function compares the files in the build directory with the published ones, returning
+/// the changes sorted by path
+pub fn diff_files(
+    local: &BTreeMap<String, FileInfo>,
+    remote: &BTreeMap<String, FileInfo>,
+) -> Vec<FileChange> {
+    let mut changes = vec![];
+    for (path, file) in local {
+        let (kind, old_size) = match remote.get(path) {
+            None => (ChangeKind::Added, None),
+            Some(old) if old.cid != file.cid => (ChangeKind::Changed, Some(old.size)),
+            Some(_) => continue,
+        };
+        changes.push(FileChange {
+            path: path.clone(),
+            kind,
+            old_size,
+            new_size: Some(file.size),
+        });
+    }
+    for (path, file) in remote {
+        if !local.contains_key(path) {
+            changes.push(FileChange {
+                path: path.clone(),
+                kind: ChangeKind::Removed,
+                old_size: Some(file.size),
+                new_size: None,
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Returns a directory under the system's temporary directory that no other diff uses
+fn scratch_dir() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNT.fetch_add(1, Ordering::SeqCst);
+    std::env::temp_dir().join(format!("fission-stage-{}-{}", std::process::id(), n))
+}
+
+/// Pairs every file in the build directory with the CID `Ipfs::hash` computed for it
+fn local_files(
+    build_dir: &Path,
+    filter: &FileFilter,
+    hashes: &HashMap<String, String>,
+) -> Result<BTreeMap<String, FileInfo>> {
+    let root = build_dir.file_name().map(Path::new).unwrap_or(build_dir);
+    let mut files = BTreeMap::new();
+    for entry in filter.walk(build_dir)? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let relative = path.strip_prefix(build_dir)?;
+        let cid = hashes
+            .get(root.join(relative).to_string_lossy().as_ref())
+            .with_context(|| format!("IPFS did not return a CID for {:?}", relative))?;
+        let size = path
+            .metadata()
+            .with_context(|| format!("Failed to read {:?}", path))?
+            .len();
+        files.insert(
+            relative_key(build_dir, path)?,
+            FileInfo {
+                cid: cid.clone(),
+                size,
+            },
+        );
+    }
+    Ok(files)
+}
+
+/// Lists every file under the directory with the given CID
+fn published_files<I: Ipfs + Sync>(ipfs: &I, cid: &str) -> Result<BTreeMap<String, FileInfo>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![(String::new(), cid.to_string())];
+    while let Some((prefix, cid)) = dirs.pop() {
+        for link in block_on(ipfs.ls(&cid))? {
+            let path = format!("{}{}", prefix, link.name);
+            if link.is_dir {
+                dirs.push((format!("{}/", path), link.cid));
+            } else {
+                files.insert(
+                    path,
+                    FileInfo {
+                        cid: link.cid,
+                        size: link.size,
+                    },
+                );
+            }
+        }
+    }
+    Ok(files)
+}
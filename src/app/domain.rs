@@ -0,0 +1,163 @@
+use std::fmt;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::utils::dns::Resolver;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum RecordKind {
+    /// A CNAME at the apex of a zone, which DNS providers resolve themselves. Some call it ANAME
+    /// or CNAME flattening.
+    #[serde(rename = "ALIAS")]
+    Alias,
+    #[serde(rename = "CNAME")]
+    Cname,
+    #[serde(rename = "TXT")]
+    Txt,
+}
+
+impl fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordKind::Alias => write!(f, "ALIAS"),
+            RecordKind::Cname => write!(f, "CNAME"),
+            RecordKind::Txt => write!(f, "TXT"),
+        }
+    }
+}
+
+/// A DNS record that has to exist for a custom domain to work
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DnsRecord {
+    pub kind: RecordKind,
+    pub name: String,
+    pub value: String,
+}
+
+impl fmt::Display for DnsRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<6} {:<40} {}", self.kind, self.name, self.value)
+    }
+}
+
+/// This function lists the records that point `domain` at the app at `url`: a CNAME for browsers
+/// and a DNSLink for IPFS. Apex domains can't have a CNAME, so they get an ALIAS instead.
+pub fn records(url: &str, domain: &str) -> Vec<DnsRecord> {
+    vec![
+        DnsRecord {
+            kind: if is_apex(domain) {
+                RecordKind::Alias
+            } else {
+                RecordKind::Cname
+            },
+            name: domain.to_string(),
+            value: url.to_string(),
+        },
+        DnsRecord {
+            kind: RecordKind::Txt,
+            name: format!("_dnslink.{}", domain),
+            value: format!("dnslink=/ipns/{}", url),
+        },
+    ]
+}
+
+/// This function looks up a record, returning why it isn't set up as expected if it isn't
+pub fn check_record<R: Resolver>(resolver: &R, record: &DnsRecord) -> Result<Option<String>> {
+    match record.kind {
+        // Flattened, so it can only be told apart by where it leads
+        RecordKind::Alias => {
+            let addresses = resolver.a(&record.name)?;
+            let expected = resolver.a(&record.value)?;
+            if addresses.is_empty() {
+                Ok(Some("is missing".to_string()))
+            } else if addresses.iter().any(|address| expected.contains(address)) {
+                Ok(None)
+            } else {
+                Ok(Some(format!("points to {}", addresses.join(", "))))
+            }
+        }
+        RecordKind::Cname => {
+            let target = resolver.cname(&record.name)?;
+            match target {
+                Some(target) if target.eq_ignore_ascii_case(&record.value) => Ok(None),
+                Some(target) => Ok(Some(format!("points to {}", target))),
+                None => Ok(Some("is missing".to_string())),
+            }
+        }
+        RecordKind::Txt => {
+            let values = resolver.txt(&record.name)?;
+            if values.contains(&record.value) {
+                Ok(None)
+            } else if values.is_empty() {
+                Ok(Some("is missing".to_string()))
+            } else {
+                Ok(Some(format!("is {}", values.join(", "))))
+            }
+        }
+    }
+}
+
+/// This function returns the records to create for serving the app at `url` at `domain`. The
+/// server has no API for custom domains, so nothing is sent to it.
+pub fn add(url: &str, domain: &str) -> Result<Vec<DnsRecord>> {
+    check_domain(domain)?;
+    Ok(records(url, domain))
+}
+
+/// This function checks that the records of a custom domain of the app at `url` are in place. DNS
+/// changes can take a while to spread, so failing here is no reason to worry.
+pub fn verify<R: Resolver>(resolver: &R, url: &str, domain: &str) -> Result<()> {
+    check_domain(domain)?;
+    let mut problems = vec![];
+    for record in records(url, domain) {
+        match check_record(resolver, &record)? {
+            None => println!("  {} {}", "✓".green(), record),
+            Some(problem) => {
+                println!("  {} {}", "✗".red(), record);
+                problems.push(format!("{} {} {}", record.kind, record.name, problem));
+            }
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "{} isn't set up yet:\n  {}\nDNS changes can take a while to show up, try again later.",
+            domain,
+            problems.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+/// Second-level labels under which country code domains are registered, as in `example.co.uk`
+const SECOND_LEVEL_LABELS: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// This function returns whether `domain` is the apex of its zone, as in `example.com`, where a
+/// CNAME can't go. Without the public suffix list, names such as `example.co.uk` are told apart
+/// by their common second-level labels.
+pub fn is_apex(domain: &str) -> bool {
+    let labels: Vec<_> = domain.trim_end_matches('.').split('.').collect();
+    match labels.as_slice() {
+        [_, _] => true,
+        [_, second, tld] => tld.len() == 2 && SECOND_LEVEL_LABELS.contains(second),
+        _ => false,
+    }
+}
+
+/// Checks that `domain` is a domain name the app can be served at
+pub fn check_domain(domain: &str) -> Result<()> {
+    let labels: Vec<_> = domain.split('.').collect();
+    let valid = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        bail!("{:?} is not a valid domain name", domain);
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use colored::Colorize;
+use futures::executor::block_on;
+use serde::{Deserialize, Serialize};
+
+use crate::server::FissionServer;
+use crate::utils::config::{APP_STATE_DIR, HISTORY_FILE};
+use crate::utils::file_management::format_size;
+
+/// A single publish or rollback of an app
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deploy {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub url: String,
+    pub cid: String,
+    /// The git commit checked out in the app directory, if it is a repository
+    pub commit: Option<String>,
+    /// The DID of the key that signed the update
+    pub did: String,
+    /// The total size of the published files, unknown for rollbacks
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rollback: bool,
+}
+
+fn is_false(x: &bool) -> bool {
+    !x
+}
+
+impl Deploy {
+    /// This method describes a deploy of `cid` to `url` happening now
+    pub fn now(app_dir: &Path, url: &str, cid: &str, did: &str) -> Result<Self> {
+        Ok(Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            url: url.to_string(),
+            cid: cid.to_string(),
+            commit: git_commit(app_dir),
+            did: did.to_string(),
+            size: None,
+            rollback: false,
+        })
+    }
+}
+
+/// This function reads the deploys recorded in `app_dir`, oldest first
+pub fn load(app_dir: &Path) -> Result<Vec<Deploy>> {
+    let path = app_dir.join(APP_STATE_DIR).join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents = std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_slice(&contents).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+/// This function reads the deploys of one app recorded in `app_dir`, oldest first. Environments
+/// and previews publish to apps of their own, so their deploys are kept apart.
+pub fn load_for(app_dir: &Path, url: &str) -> Result<Vec<Deploy>> {
+    Ok(load(app_dir)?
+        .into_iter()
+        .filter(|deploy| deploy.url == url)
+        .collect())
+}
+
+/// This function adds a deploy to the end of the history in `app_dir`
+pub fn append(app_dir: &Path, deploy: Deploy) -> Result<()> {
+    let mut history = load(app_dir)?;
+    history.push(deploy);
+    let dir = app_dir.join(APP_STATE_DIR);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    let path = dir.join(HISTORY_FILE);
+    std::fs::write(&path, serde_json::to_vec_pretty(&history)?)
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// This function picks the CID to roll back to from the deploys of one app, oldest first. `to`
+/// can be a CID or how many deploys to go back, as numbered by `fission app history`. Without
+/// it, the last deploy of a different CID than the current one is picked.
+pub fn rollback_target(history: &[Deploy], to: Option<&str>) -> Result<String> {
+    if let Some(to) = to {
+        return match to.parse::<usize>() {
+            Ok(n) => history
+                .iter()
+                .rev()
+                .nth(n)
+                .map(|deploy| deploy.cid.clone())
+                .with_context(|| format!("Only {} deploys were recorded", history.len())),
+            Err(_) => Ok(to.to_string()),
+        };
+    }
+    let current = match history.last() {
+        Some(x) => &x.cid,
+        None => bail!("No deploys were recorded in this directory, pass --to <CID> instead"),
+    };
+    history
+        .iter()
+        .rev()
+        .find(|deploy| deploy.cid != *current)
+        .map(|deploy| deploy.cid.clone())
+        .context("There is no earlier deploy to roll back to")
+}
+
+/// This function points the app at `url` back at an earlier CID, without uploading anything, and
+/// records the rollback in the history of `app_dir`.
+pub fn rollback<S: FissionServer + Sync>(
+    server: &S,
+    app_dir: &Path,
+    url: &str,
+    to: Option<&str>,
+) -> Result<Deploy> {
+    let cid = rollback_target(&load_for(app_dir, url)?, to)?;
+
+    println!("🛰️  Pointing {} at {}...", url, cid.bright_blue());
+    block_on(server.update_app(url, &cid, false))?;
+
+    let mut deploy = Deploy::now(app_dir, url, &cid, server.did())?;
+    deploy.rollback = true;
+    append(app_dir, deploy.clone())?;
+    Ok(deploy)
+}
+
+/// This function prints the deploys of one app newest first, numbered the way `rollback --to`
+/// expects
+pub fn print(history: &[Deploy]) {
+    if history.is_empty() {
+        println!("Nothing was published from this directory yet");
+        return;
+    }
+    for (n, deploy) in history.iter().rev().enumerate() {
+        let mut details = vec![];
+        if let Some(commit) = &deploy.commit {
+            details.push(format!("commit {}", &commit[..commit.len().min(8)]));
+        }
+        if let Some(size) = deploy.size {
+            details.push(format_size(size));
+        }
+        if deploy.rollback {
+            details.push("rollback".to_string());
+        }
+        println!(
+            "{:>3}  {}  {}  {}",
+            n,
+            format_timestamp(deploy.timestamp),
+            deploy.cid.bright_blue(),
+            details.join(", ")
+        );
+        println!("     {} by {}", deploy.url, deploy.did);
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, such as `2022-09-14 16:05:23`
+pub fn format_timestamp(timestamp: u64) -> String {
+    to_datetime(timestamp)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 date and time, such as
+/// `2022-09-14T16:05:23Z`
+pub fn rfc3339_timestamp(timestamp: u64) -> String {
+    to_datetime(timestamp).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Parses an RFC 3339 date and time, such as `2022-09-14T16:05:23Z`, into seconds since the Unix
+/// epoch. Fractions of a second are dropped.
+pub fn parse_timestamp(s: &str) -> Option<u64> {
+    let datetime = DateTime::parse_from_rfc3339(s).ok()?;
+    u64::try_from(datetime.timestamp()).ok()
+}
+
+fn to_datetime(timestamp: u64) -> DateTime<Utc> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// Returns the commit checked out in `dir`, if it is in a git repository
+fn git_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
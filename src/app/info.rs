@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+use serde::Serialize;
+
+use crate::app::config::AppConfig;
+use crate::app::history::{self, rfc3339_timestamp};
+use crate::server::FissionServer;
+use crate::utils::file_management::format_size;
+
+/// The app config and what the server knows about the app, as `fission app info` shows them
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AppSummary {
+    pub url: String,
+    /// Every URL the app is served at, including custom domains
+    pub urls: Vec<String>,
+    pub cid: Option<String>,
+    /// When the app was last published, in RFC 3339 format
+    pub published_at: Option<String>,
+    /// The total size of the published files in bytes
+    pub size: Option<u64>,
+    pub domains: Vec<String>,
+    pub owner: Option<String>,
+    pub build_dir: PathBuf,
+}
+
+/// This function gathers what is known about the app in `app_dir`. The server's answer is
+/// completed with the deploy history of `app_dir` when it leaves anything out.
+pub fn info<S: FissionServer + Sync>(server: &S, app_dir: &Path) -> Result<AppSummary> {
+    let config = AppConfig::load(app_dir)?;
+    let app = match block_on(server.app_info(&config.url))? {
+        Some(app) => app,
+        None => bail!(
+            "The server doesn't know {}. It may have been deleted, or registered with another \
+             account.",
+            config.url
+        ),
+    };
+    let last_deploy = history::load(app_dir)?
+        .into_iter()
+        .rev()
+        .find(|deploy| deploy.url == config.url && app.cid.as_ref() == Some(&deploy.cid));
+
+    let mut urls = app.urls;
+    if urls.is_empty() {
+        urls.push(config.url.clone());
+    }
+    Ok(AppSummary {
+        urls: urls.iter().map(|url| format!("https://{}", url)).collect(),
+        url: config.url,
+        published_at: app.published_at.or_else(|| {
+            last_deploy
+                .as_ref()
+                .map(|deploy| rfc3339_timestamp(deploy.timestamp))
+        }),
+        size: app
+            .size
+            .or_else(|| last_deploy.as_ref().and_then(|deploy| deploy.size)),
+        cid: app.cid,
+        domains: app.domains,
+        owner: app.owner,
+        build_dir: config.build.dir,
+    })
+}
+
+impl AppSummary {
+    pub fn print(&self) {
+        let unknown = || "-".to_string();
+        let rows = [
+            ("URL", self.urls.join(", ")),
+            (
+                "Domains",
+                if self.domains.is_empty() {
+                    unknown()
+                } else {
+                    self.domains.join(", ")
+                },
+            ),
+            (
+                "CID",
+                self.cid
+                    .clone()
+                    .unwrap_or_else(|| "not published yet".to_string()),
+            ),
+            (
+                "Published",
+                self.published_at.clone().unwrap_or_else(unknown),
+            ),
+            ("Size", self.size.map(format_size).unwrap_or_else(unknown)),
+            ("Owner", self.owner.clone().unwrap_or_else(unknown)),
+            ("Build", self.build_dir.display().to_string()),
+        ];
+        for (name, value) in rows {
+            println!("{:<10} {}", name.bold(), value);
+        }
+    }
+}
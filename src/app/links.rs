@@ -0,0 +1,299 @@
+use percent_encoding::percent_decode_str;
+
+/// HTML attributes that hold a URL
+const URL_ATTRIBUTES: &[&str] = &["src", "href", "poster", "data"];
+
+/// A URL in an HTML or CSS file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    /// The URL as it is written in the file
+    pub value: String,
+    /// Where the URL is in the file, in bytes
+    pub start: usize,
+    pub end: usize,
+    /// The 1-based line it is on
+    pub line: usize,
+}
+
+/// This function finds the URLs of an HTML file: those of `src`, `href`, `poster`, `data` and
+/// `srcset` attributes, and those in inline styles and `<style>` elements. Scripts are skipped.
+pub fn html_references(html: &str) -> Vec<Reference> {
+    let lower = html.to_ascii_lowercase();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while let Some(offset) = html[i..].find('<') {
+        let start = i + offset;
+        // A `<` in text, as in `a < b`
+        if !html[start + 1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+        {
+            i = start + 1;
+            continue;
+        }
+        if html[start..].starts_with("<!--") {
+            i = match html[start..].find("-->") {
+                Some(end) => start + end + 3,
+                None => html.len(),
+            };
+            continue;
+        }
+        let end = match tag_end(html, start) {
+            Some(end) => end,
+            None => break,
+        };
+        let name: String = lower[start + 1..end]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        for (attribute, value_start, value_end) in attributes(html, start + 1, end) {
+            let value = &html[value_start..value_end];
+            if attribute == "srcset" {
+                ranges.extend(srcset_ranges(value, value_start));
+            } else if attribute == "style" {
+                ranges.extend(css_ranges(value, value_start));
+            } else if URL_ATTRIBUTES.contains(&attribute.as_str()) {
+                ranges.push((value_start, value_end));
+            }
+        }
+        i = end + 1;
+        if name == "script" || name == "style" {
+            let body_end = lower[i..]
+                .find(&format!("</{}", name))
+                .map(|end| i + end)
+                .unwrap_or(html.len());
+            if name == "style" {
+                ranges.extend(css_ranges(&html[i..body_end], i));
+            }
+            i = body_end;
+        }
+    }
+    to_references(html, ranges)
+}
+
+/// This function finds the URLs of a CSS file, in `url()` and `@import`
+pub fn css_references(css: &str) -> Vec<Reference> {
+    to_references(css, css_ranges(css, 0))
+}
+
+/// This function returns the path in the site that `reference`, found in the file at `from`,
+/// points to, or `None` for URLs of other sites, fragments of the same page and the like. Paths
+/// are relative to the root and keep a trailing `/`. Like browsers, it doesn't let `..` go above
+/// the root.
+pub fn resolve(from: &str, reference: &str) -> Option<String> {
+    let reference = reference.trim();
+    let path = reference.split(['?', '#']).next().unwrap_or("");
+    if path.is_empty() || path.starts_with("//") || has_scheme(path) {
+        return None;
+    }
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    let mut segments: Vec<&str> = if path.starts_with('/') {
+        vec![]
+    } else {
+        from.split('/').collect()
+    };
+    // The name of the file the reference is in
+    segments.pop();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut resolved = segments.join("/");
+    if path.ends_with('/') && !resolved.is_empty() {
+        resolved.push('/');
+    }
+    Some(resolved)
+}
+
+/// This function replaces the file name of a reference, keeping its directory, query and
+/// fragment as they are
+pub fn rename_reference(reference: &str, new_name: &str) -> String {
+    let end = reference.find(['?', '#']).unwrap_or(reference.len());
+    let (path, rest) = reference.split_at(end);
+    let dir_end = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    format!("{}{}{}", &path[..dir_end], new_name, rest)
+}
+
+fn has_scheme(path: &str) -> bool {
+    match path.find(':') {
+        Some(i) => {
+            let scheme = &path[..i];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
+/// Finds the `>` that ends the tag starting at `start`, skipping quoted attribute values
+fn tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return Some(start + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Lists the attributes of the tag between `start` and `end`, with their names in lowercase and
+/// where their values are
+fn attributes(html: &str, start: usize, end: usize) -> Vec<(String, usize, usize)> {
+    let bytes = html.as_bytes();
+    let is_space = |i: usize| bytes[i].is_ascii_whitespace();
+    let mut attributes = vec![];
+    // Skip the tag name
+    let mut i = start;
+    while i < end && !is_space(i) {
+        i += 1;
+    }
+    while i < end {
+        while i < end && (is_space(i) || bytes[i] == b'/') {
+            i += 1;
+        }
+        let name_start = i;
+        while i < end && !is_space(i) && bytes[i] != b'=' && bytes[i] != b'/' {
+            i += 1;
+        }
+        let name = html[name_start..i].to_ascii_lowercase();
+        while i < end && is_space(i) {
+            i += 1;
+        }
+        if i >= end || bytes[i] != b'=' {
+            continue;
+        }
+        i += 1;
+        while i < end && is_space(i) {
+            i += 1;
+        }
+        if i >= end {
+            break;
+        }
+        let (value_start, value_end) = if bytes[i] == b'"' || bytes[i] == b'\'' {
+            let quote = bytes[i];
+            let value_start = i + 1;
+            let mut j = value_start;
+            while j < end && bytes[j] != quote {
+                j += 1;
+            }
+            i = j + 1;
+            (value_start, j)
+        } else {
+            let value_start = i;
+            while i < end && !is_space(i) {
+                i += 1;
+            }
+            (value_start, i)
+        };
+        if !name.is_empty() {
+            attributes.push((name, value_start, value_end));
+        }
+    }
+    attributes
+}
+
+/// Splits a `srcset` such as `a.png 1x, b.png 2x` into the ranges of its URLs
+fn srcset_ranges(value: &str, offset: usize) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut candidate_start = 0;
+    for candidate in value.split(',') {
+        let trimmed = candidate.trim_start();
+        let start = candidate_start + candidate.len() - trimmed.len();
+        let len = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+        if len > 0 {
+            ranges.push((offset + start, offset + start + len));
+        }
+        candidate_start += candidate.len() + 1;
+    }
+    ranges
+}
+
+fn css_ranges(css: &str, offset: usize) -> Vec<(usize, usize)> {
+    let lower = css.to_ascii_lowercase();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < css.len() {
+        let rest = &lower[i..];
+        if rest.starts_with("/*") {
+            i = match rest.find("*/") {
+                Some(end) => i + end + 2,
+                None => css.len(),
+            };
+        } else if rest.starts_with("url(") {
+            let (start, end) = url_value(css, i + 4, ')');
+            ranges.push((offset + start, offset + end));
+            i = end;
+        } else if rest.starts_with("@import") {
+            let mut start = i + 7;
+            while is_css_space(css, start) {
+                start += 1;
+            }
+            // `@import url(...)` is picked up as a url
+            if css[start..].starts_with(['"', '\'']) {
+                let (start, end) = url_value(css, start, ';');
+                ranges.push((offset + start, offset + end));
+                i = end;
+            } else {
+                i = start;
+            }
+        } else {
+            i += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    ranges.retain(|(start, end)| start < end);
+    ranges
+}
+
+/// Finds the URL starting at `start`, either quoted or running up to `close`
+fn url_value(css: &str, start: usize, close: char) -> (usize, usize) {
+    let mut start = start;
+    while is_css_space(css, start) {
+        start += 1;
+    }
+    if let Some(quote) = css[start..]
+        .chars()
+        .next()
+        .filter(|c| *c == '"' || *c == '\'')
+    {
+        let end = css[start + 1..]
+            .find(quote)
+            .map(|end| start + 1 + end)
+            .unwrap_or(css.len());
+        return (start + 1, end);
+    }
+    let end = css[start..]
+        .find(|c: char| c == close || c.is_ascii_whitespace())
+        .map(|end| start + end)
+        .unwrap_or(css.len());
+    (start, end)
+}
+
+/// Whether there is whitespace at `i`. CSS only counts ASCII whitespace, and stepping over
+/// anything wider a byte at a time would land inside a character.
+fn is_css_space(css: &str, i: usize) -> bool {
+    matches!(css.as_bytes().get(i), Some(b) if b.is_ascii_whitespace())
+}
+
+fn to_references(contents: &str, mut ranges: Vec<(usize, usize)>) -> Vec<Reference> {
+    ranges.sort_unstable();
+    ranges.dedup();
+    ranges
+        .into_iter()
+        .map(|(start, end)| Reference {
+            value: contents[start..end].to_string(),
+            start,
+            end,
+            line: contents[..start].matches('\n').count() + 1,
+        })
+        .filter(|reference| !reference.value.trim().is_empty())
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::config::AppConfig;
+use crate::app::register::check_name;
+use crate::server::{AppInfo, FissionServer};
+
+/// This function lists the current user's apps, sorted by URL
+pub fn list<S: FissionServer + Sync>(server: &S) -> Result<Vec<AppInfo>> {
+    let mut apps = block_on(server.list_apps())?;
+    apps.sort_by(|a, b| a.urls.cmp(&b.urls));
+    Ok(apps)
+}
+
+pub fn print_list(apps: &[AppInfo]) {
+    if apps.is_empty() {
+        println!("You don't have any apps yet. Run `fission app register` to create one.");
+        return;
+    }
+    for app in apps {
+        let url = app.urls.first().map(String::as_str).unwrap_or("-");
+        let cid = app.cid.as_deref().unwrap_or("not published yet");
+        println!("{:<40} {}", url.bright_blue(), cid);
+    }
+}
+
+/// This function finds the URL of one of the user's apps. A full URL is taken as it is, while a
+/// bare name such as `my-app` is looked up among the user's apps.
+pub fn resolve_url<S: FissionServer + Sync>(server: &S, name: &str) -> Result<String> {
+    if name.contains('.') {
+        return Ok(name.to_string());
+    }
+    let prefix = format!("{}.", name);
+    let url = block_on(server.list_apps())?
+        .into_iter()
+        .flat_map(|app| app.urls)
+        .find(|url| url.starts_with(&prefix));
+    match url {
+        Some(url) => Ok(url),
+        None => bail!("You don't have an app named {:?}", name),
+    }
+}
+
+/// This function moves an app to a new subdomain, returning its new URL. The server can't rename
+/// apps, so this creates the new one, points it at the published CID and then destroys the old
+/// one, which is the only step needing Destroy potency. If the app config in `app_dir` is for
+/// that app, it is updated too.
+pub fn rename<S: FissionServer + Sync>(
+    server: &S,
+    app_dir: &Path,
+    name: &str,
+    new_name: &str,
+) -> Result<String> {
+    check_name(new_name)?;
+    let url = resolve_url(server, name)?;
+    let cid = block_on(server.app_cid(&url))?;
+    let new_url = block_on(server.create_app(Some(new_name)))?;
+    if let Some(cid) = cid {
+        // The server pins the data already, so there is nothing to copy
+        block_on(server.update_app(&new_url, &cid, false))?;
+    }
+    if AppConfig::find(app_dir)?.is_some() {
+        let mut config = AppConfig::load(app_dir)?;
+        if config.url == url {
+            config.url = new_url.clone();
+            config.save(app_dir)?;
+        }
+    }
+    block_on(server.destroy_app(&url)).with_context(|| {
+        format!(
+            "Created {}, but {} is still there. Destroy it with `fission app destroy {}`.",
+            new_url, url, url
+        )
+    })?;
+    Ok(new_url)
+}
+
+/// This function asks for the app's URL to be typed back before it is destroyed, failing if it
+/// isn't
+pub fn confirm_destroy<R: BufRead>(input: R, url: &str) -> Result<()> {
+    print!(
+        "{} This deletes {} for good. Type its URL to confirm: ",
+        "⚠️ ".yellow(),
+        url.bright_blue()
+    );
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    input.take(1024).read_line(&mut answer)?;
+    if answer.trim() != url {
+        bail!("The URL didn't match, {} was not destroyed", url);
+    }
+    Ok(())
+}
+
+/// This function deletes the app at `url`, which needs a UCAN with Destroy potency
+pub fn destroy<S: FissionServer + Sync>(server: &S, url: &str) -> Result<()> {
+    block_on(server.destroy_app(url))
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::utils::config::{APP_STATE_DIR, PUBLISH_STATE_FILE};
+use crate::utils::file_filter::FileFilter;
+
+/// The size and SHA-256 of a published file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Every file in the build directory by its path relative to it, separated by `/`
+pub type Manifest = BTreeMap<String, FileDigest>;
+
+/// What was uploaded the last time the app was published from this directory
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishState {
+    pub url: String,
+    pub cid: String,
+    pub files: Manifest,
+}
+
+impl PublishState {
+    /// Reads the state `save` left in `app_dir`, if there is any
+    pub fn load(app_dir: &Path) -> Result<Option<Self>> {
+        let path = app_dir.join(APP_STATE_DIR).join(PUBLISH_STATE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents =
+            std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        // A state file we can't understand only costs us a full upload
+        Ok(serde_json::from_slice(&contents).ok())
+    }
+
+    pub fn save(&self, app_dir: &Path) -> Result<()> {
+        let dir = app_dir.join(APP_STATE_DIR);
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let path = dir.join(PUBLISH_STATE_FILE);
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// Whether publishing `files` to `url` would upload exactly what was uploaded last time
+    pub fn is_up_to_date(&self, url: &str, files: &Manifest) -> bool {
+        self.url == url && self.files == *files
+    }
+}
+
+/// This function hashes every file the filter lets through, which is much cheaper than adding the
+/// directory to IPFS again.
+pub fn build_manifest(build_dir: &Path, filter: &FileFilter) -> Result<Manifest> {
+    let mut manifest = Manifest::new();
+    for entry in filter.walk(build_dir)? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        // Stream the file through the hash, as build directories can hold large assets
+        let mut file = File::open(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        manifest.insert(
+            relative_key(build_dir, path)?,
+            FileDigest {
+                size,
+                sha256: format!("{:x}", hasher.finalize()),
+            },
+        );
+    }
+    Ok(manifest)
+}
+
+/// Turns a path in the build directory into the key used for it in manifests and plans
+pub fn relative_key(build_dir: &Path, path: &Path) -> Result<String> {
+    let key: Vec<_> = path
+        .strip_prefix(build_dir)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Ok(key.join("/"))
+}
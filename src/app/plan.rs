@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::check::{check_links, BrokenLink};
+use crate::app::diff::{AppDiff, FileChange};
+use crate::app::publish::{build_dir, file_filter, load_config, PublishOptions};
+use crate::app::routing::Routing;
+use crate::app::scan::{scan, Finding};
+use crate::ipfs::Ipfs;
+use crate::server::FissionServer;
+use crate::utils::file_filter::FileFilter;
+use crate::utils::file_management::format_size;
+
+/// What publishing the app would do
+pub struct DeployPlan {
+    pub url: String,
+    /// The CID the files would get, or `None` if no IPFS node was running to hash them with
+    pub cid: Option<String>,
+    /// The CID the app currently points to, if it was published before
+    pub current_cid: Option<String>,
+    /// The changed files, or `None` if the files couldn't be compared with the published ones
+    pub changes: Option<Vec<FileChange>>,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub update_dns: bool,
+    /// Files that would stop the publish unless `--allow-sensitive` is passed
+    pub sensitive: Vec<Finding>,
+    /// The redirects the gateway would follow, and the headers only `fission app serve` applies
+    pub routing: Routing,
+    /// References that would stop the publish unless `--skip-check` is passed
+    pub broken: Vec<BrokenLink>,
+}
+
+/// This function works out what publishing the app in `app_dir` would change, without writing
+/// anything to IPFS, the server or `app_dir`. The files are only hashed if `ipfs` is given,
+/// which should be a node that is already running. Listing the published files may fetch them
+/// from the swarm.
+pub fn plan<I, S>(
+    ipfs: Option<&I>,
+    server: &S,
+    app_dir: &Path,
+    options: &PublishOptions,
+) -> Result<DeployPlan>
+where
+    I: Ipfs + Sync,
+    S: FissionServer + Sync,
+{
+    let config = load_config(app_dir, options)?;
+    let build_dir = build_dir(app_dir, &config)?;
+    let filter = file_filter(&config, options.include_hidden);
+    let sensitive = scan(&build_dir, &filter, &config.allow_sensitive)?;
+    let routing = Routing::load(&config.routing, &build_dir)?;
+    let broken = if options.skip_check {
+        vec![]
+    } else {
+        check_links(&build_dir, &filter, &routing)?
+    };
+
+    let (cid, current_cid, changes, sizes) = match ipfs {
+        Some(ipfs) => {
+            let diff = AppDiff::compute(ipfs, server, app_dir, &config, options.include_hidden)?;
+            let changes = match diff.changes(ipfs) {
+                Ok(changes) => Some(changes),
+                Err(e) => {
+                    eprintln!("{} {:#}", "Couldn't list the published files:".yellow(), e);
+                    None
+                }
+            };
+            let sizes = diff.files.values().map(|file| file.size).collect();
+            (Some(diff.cid), diff.current_cid, changes, sizes)
+        }
+        None => {
+            let current_cid = block_on(server.app_cid(&config.url))?;
+            (None, current_cid, None, file_sizes(&build_dir, &filter)?)
+        }
+    };
+
+    Ok(DeployPlan {
+        update_dns: options.update_dns && current_cid != cid,
+        file_count: sizes.len(),
+        total_bytes: sizes.iter().sum(),
+        url: config.url,
+        cid,
+        current_cid,
+        changes,
+        sensitive,
+        routing,
+        broken,
+    })
+}
+
+/// Lists the sizes of the files in the build directory, for when they can't be hashed
+fn file_sizes(build_dir: &Path, filter: &FileFilter) -> Result<Vec<u64>> {
+    let mut sizes = vec![];
+    for entry in filter.walk(build_dir)? {
+        let path = entry.path();
+        if path.is_file() {
+            let metadata = path
+                .metadata()
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            sizes.push(metadata.len());
+        }
+    }
+    Ok(sizes)
+}
+
+impl DeployPlan {
+    pub fn print(&self) {
+        println!("📋 Deploy plan for {}", self.url.bright_blue());
+        match &self.changes {
+            Some(changes) if changes.is_empty() => println!("  No files changed"),
+            Some(changes) => {
+                for change in changes {
+                    change.print();
+                }
+            }
+            None => println!("  Changed files unknown"),
+        }
+        println!(
+            "  {} files, {} total",
+            self.file_count,
+            format_size(self.total_bytes)
+        );
+        let cid = match &self.cid {
+            Some(cid) => cid.bright_blue(),
+            None => "unknown, no IPFS node is running to hash the files with".yellow(),
+        };
+        match &self.current_cid {
+            Some(current) => println!("  CID: {} (currently {})", cid, current),
+            None => println!("  CID: {} (not published yet)", cid),
+        }
+        if self.update_dns {
+            println!("  DNS would be updated");
+        } else {
+            println!("  DNS would be left unchanged");
+        }
+        if !self.routing.redirects.is_empty() {
+            println!("  Redirects:");
+            for rule in &self.routing.redirects {
+                println!("    {}", rule);
+            }
+        }
+        if !self.routing.headers.is_empty() {
+            println!("  Headers, which aren't published and only apply under `fission app serve`:");
+            for rule in &self.routing.headers {
+                for (name, value) in &rule.headers {
+                    println!("    {} {}: {}", rule.path, name, value);
+                }
+            }
+        }
+        if !self.sensitive.is_empty() {
+            println!("{}", "⚠️  These files look sensitive:".yellow());
+            for finding in &self.sensitive {
+                println!("  {} {}", finding.path, finding.reason);
+            }
+        }
+        if !self.broken.is_empty() {
+            println!(
+                "{}",
+                "⚠️  These references point at missing files:".yellow()
+            );
+            for link in &self.broken {
+                link.print();
+            }
+        }
+    }
+}
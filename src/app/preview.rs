@@ -0,0 +1,147 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::config::AppConfig;
+use crate::app::history::{format_timestamp, parse_timestamp};
+use crate::server::FissionServer;
+use crate::utils::config::PREVIEW_INFIX;
+
+/// A preview deployment of the app, found on the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preview {
+    pub url: String,
+    pub cid: Option<String>,
+    /// When it was last published, in seconds since the Unix epoch
+    pub published_at: Option<u64>,
+    /// Whether it wasn't published for longer than the previews live
+    pub expired: bool,
+}
+
+/// This function returns the URL the preview of `branch` is published to, such as
+/// `my-app-preview-feature-x.fission.app` for the branch `feature/x` of `my-app.fission.app`
+pub fn preview_url(base_url: &str, branch: &str) -> Result<String> {
+    let (base, domain) = base_url
+        .split_once('.')
+        .with_context(|| format!("Cannot preview {}, it has no subdomain", base_url))?;
+    let mut slug = String::new();
+    for c in branch.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let prefix = format!("{}{}", base, PREVIEW_INFIX);
+    if slug.is_empty() {
+        bail!("Cannot name a preview after the branch {:?}", branch);
+    }
+    if prefix.len() >= 63 {
+        bail!(
+            "The subdomain of {} is too long to add a preview suffix",
+            base_url
+        );
+    }
+    // Subdomains can't be longer than 63 characters
+    let name: String = format!("{}{}", prefix, slug).chars().take(63).collect();
+    let name = name.trim_end_matches('-');
+    Ok(format!("{}.{}", name, domain))
+}
+
+/// This function returns the config for publishing a preview of `branch`
+pub fn preview_config(config: &AppConfig, branch: &str) -> Result<AppConfig> {
+    let mut preview = config.clone();
+    preview.url = preview_url(&config.url, branch)?;
+    Ok(preview)
+}
+
+/// This function creates the app at `url` if the server doesn't know it yet
+pub fn ensure_app<S: FissionServer + Sync>(server: &S, url: &str) -> Result<()> {
+    if block_on(server.app_info(url))?.is_some() {
+        return Ok(());
+    }
+    let name = url.split('.').next().unwrap_or(url);
+    let created = block_on(server.create_app(Some(name)))?;
+    if created != url {
+        bail!("The server created {} rather than {}", created, url);
+    }
+    println!("✨ Created the preview app {}", url.bright_blue());
+    Ok(())
+}
+
+/// This function lists the previews of the app the server knows about, sorted by URL
+pub fn previews<S: FissionServer + Sync>(server: &S, config: &AppConfig) -> Result<Vec<Preview>> {
+    let (base, domain) = config
+        .url
+        .split_once('.')
+        .with_context(|| format!("{} has no previews", config.url))?;
+    let prefix = format!("{}{}", base, PREVIEW_INFIX);
+    let suffix = format!(".{}", domain);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let ttl = config.previews.ttl_days * 86400;
+
+    let mut previews = vec![];
+    for app in block_on(server.list_apps())? {
+        let published_at = app.published_at.as_deref().and_then(parse_timestamp);
+        for url in &app.urls {
+            if url.starts_with(&prefix) && url.ends_with(&suffix) {
+                previews.push(Preview {
+                    url: url.clone(),
+                    cid: app.cid.clone(),
+                    published_at,
+                    expired: matches!(published_at, Some(t) if now.saturating_sub(t) > ttl),
+                });
+            }
+        }
+    }
+    previews.sort_by(|a, b| a.url.cmp(&b.url));
+    Ok(previews)
+}
+
+pub fn print_previews(previews: &[Preview]) {
+    if previews.is_empty() {
+        println!("There are no previews");
+    }
+    for preview in previews {
+        let published = preview
+            .published_at
+            .map(format_timestamp)
+            .unwrap_or_else(|| "never published".to_string());
+        let status = if preview.expired {
+            "expired".yellow()
+        } else {
+            "".normal()
+        };
+        println!(
+            "{:<50} {}  {}",
+            preview.url.bright_blue(),
+            published,
+            status
+        );
+    }
+}
+
+/// This function deletes the expired previews of the app, or all of them with `all`, returning
+/// the URLs of the deleted ones. Previews that were never published only go with `all`, since
+/// there is no telling how old they are.
+pub fn prune<S: FissionServer + Sync>(
+    server: &S,
+    config: &AppConfig,
+    all: bool,
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let mut pruned = vec![];
+    for preview in previews(server, config)? {
+        if !(all || preview.expired) {
+            continue;
+        }
+        if !dry_run {
+            block_on(server.destroy_app(&preview.url))?;
+        }
+        pruned.push(preview.url);
+    }
+    Ok(pruned)
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::build::run_build;
+use crate::app::check::{self, check_links};
+use crate::app::config::AppConfig;
+use crate::app::history::{self, Deploy};
+use crate::app::manifest::{build_manifest, PublishState};
+use crate::app::preview::{ensure_app, preview_config};
+use crate::app::routing::Routing;
+use crate::app::scan::scan;
+use crate::app::stage::stage;
+use crate::ipfs::peering::ensure_cluster_connectivity;
+use crate::ipfs::Ipfs;
+use crate::server::FissionServer;
+use crate::utils::config::HEADERS_FILE;
+use crate::utils::file_filter::FileFilter;
+
+pub struct PublishOptions {
+    /// Upload the files to IPFS. Without this, the CID is only computed.
+    pub update_data: bool,
+    /// Point the app and its DNSLink at the new CID
+    pub update_dns: bool,
+    /// The cluster peers to connect to before uploading
+    pub peers: Vec<String>,
+    /// Add the cluster peers to the daemon's `Peering.Peers`. Only for the daemon the CLI manages,
+    /// as the config of a remote node isn't ours to change.
+    pub persist_peering: bool,
+    /// Publish dot files that aren't explicitly ignored
+    pub include_hidden: bool,
+    /// Publish files that look like they contain secrets, only warning about them
+    pub allow_sensitive: bool,
+    /// Upload even if nothing changed since the last publish from this directory
+    pub force: bool,
+    /// Don't run the build command from the app config before publishing
+    pub skip_build: bool,
+    /// Don't check the build directory for broken links before publishing
+    pub skip_check: bool,
+    /// The environment from the app config to publish to
+    pub env: Option<String>,
+    /// Publish to the preview app of this branch, creating it if needed
+    pub preview: Option<String>,
+    pub verbose: bool,
+}
+
+pub struct Published {
+    pub cid: String,
+    /// The URL the app is served at, including the scheme
+    pub url: String,
+}
+
+/// This function publishes the build directory of the app in `app_dir`: it adds the files to
+/// IPFS, then asks the server to point the app at the resulting CID. The app's build command runs
+/// first, unless `skip_build` is set. If the files are the same as the last time they were
+/// uploaded from `app_dir`, the upload is skipped.
+pub fn publish<I, S>(
+    ipfs: &I,
+    server: &S,
+    app_dir: &Path,
+    options: &PublishOptions,
+) -> Result<Published>
+where
+    I: Ipfs + Sync,
+    S: FissionServer + Sync,
+{
+    let config = load_config(app_dir, options)?;
+    if options.preview.is_some() && options.update_dns {
+        ensure_app(server, &config.url)?;
+    }
+    if !options.skip_build {
+        run_build(app_dir, &config.build)?;
+    }
+    let build_dir = build_dir(app_dir, &config)?;
+    let filter = file_filter(&config, options.include_hidden);
+    let upload_dir = stage(app_dir, &config, &build_dir, &filter)?;
+
+    let manifest = if options.update_data {
+        Some(build_manifest(&upload_dir, &filter)?)
+    } else {
+        None
+    };
+    if let (Some(manifest), false) = (&manifest, options.force) {
+        match PublishState::load(app_dir)? {
+            Some(state) if state.is_up_to_date(&config.url, manifest) => {
+                println!("✅ Already up to date ({})", state.cid.bright_blue());
+                if options.update_dns {
+                    println!("🛰️  Updating {}...", config.url);
+                    block_on(server.update_app(&config.url, &state.cid, false))?;
+                    let mut deploy = Deploy::now(app_dir, &config.url, &state.cid, server.did())?;
+                    deploy.size = Some(manifest.values().map(|file| file.size).sum());
+                    history::append(app_dir, deploy)?;
+                }
+                return Ok(Published {
+                    cid: state.cid,
+                    url: format!("https://{}", config.url),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let hashes = if options.update_data {
+        check_sensitive(&build_dir, &config, &filter, options)?;
+        check_broken_links(&build_dir, &config, &filter, options)?;
+        println!("🌐 Connecting to the Fission IPFS cluster...");
+        let health = block_on(ensure_cluster_connectivity(
+            ipfs,
+            &options.peers,
+            options.persist_peering,
+        ))?;
+        if options.verbose {
+            for peer in health {
+                match peer.result {
+                    Ok(latency) => println!("  ✅ {} ({:?})", peer.addr, latency),
+                    Err(e) => println!("  ❌ {}\n     {}", peer.addr, e.red()),
+                }
+            }
+        }
+        println!("📦 Uploading {}...", build_dir.display());
+        block_on(ipfs.add(&upload_dir, &filter))?
+    } else {
+        block_on(ipfs.hash(&upload_dir, &filter))?
+    };
+    let cid = root_cid(&upload_dir, &hashes)?;
+    println!("🔗 CID: {}", cid.bright_blue());
+    let size = manifest
+        .as_ref()
+        .map(|files| files.values().map(|file| file.size).sum());
+    if let Some(files) = manifest {
+        let state = PublishState {
+            url: config.url.clone(),
+            cid: cid.clone(),
+            files,
+        };
+        state.save(app_dir)?;
+    }
+
+    // Only what the app points at is a deploy, as rollbacks take the last one to be live
+    if options.update_dns {
+        println!("🛰️  Updating {}...", config.url);
+        block_on(server.update_app(&config.url, &cid, options.update_data))?;
+        let mut deploy = Deploy::now(app_dir, &config.url, &cid, server.did())?;
+        deploy.size = size;
+        history::append(app_dir, deploy)?;
+    }
+
+    Ok(Published {
+        cid,
+        url: format!("https://{}", config.url),
+    })
+}
+
+/// This function loads the app config of `app_dir` as it applies to the environment or preview
+/// being published
+pub fn load_config(app_dir: &Path, options: &PublishOptions) -> Result<AppConfig> {
+    config_for(app_dir, options.env.as_deref(), options.preview.as_deref())
+}
+
+/// This function loads the app config of `app_dir` as it applies to the named environment or the
+/// preview of a branch, if any
+pub fn config_for(app_dir: &Path, env: Option<&str>, preview: Option<&str>) -> Result<AppConfig> {
+    let mut config = AppConfig::load(app_dir)?;
+    if let Some(env) = env {
+        config = config.for_env(env)?;
+    }
+    if let Some(branch) = preview {
+        config = preview_config(&config, branch)?;
+    }
+    Ok(config)
+}
+
+/// Resolves the app's build directory to an absolute path, so it always has a name to look up
+/// in the hashes `Ipfs::add` returns
+pub fn build_dir(app_dir: &Path, config: &AppConfig) -> Result<PathBuf> {
+    let build_dir = app_dir.join(&config.build.dir);
+    std::fs::canonicalize(&build_dir)
+        .with_context(|| format!("The build directory {:?} does not exist", build_dir))
+}
+
+/// This function scans the files about to be uploaded, failing if any of them look like they
+/// hold secrets unless `allow_sensitive` is set. Once on IPFS, files can't be taken back.
+pub fn check_sensitive(
+    build_dir: &Path,
+    config: &AppConfig,
+    filter: &FileFilter,
+    options: &PublishOptions,
+) -> Result<()> {
+    let findings = scan(build_dir, filter, &config.allow_sensitive)?;
+    if findings.is_empty() {
+        return Ok(());
+    }
+    println!("{}", "⚠️  These files look sensitive:".yellow());
+    for finding in &findings {
+        println!("  {} {}", finding.path, finding.reason);
+    }
+    if !options.allow_sensitive {
+        bail!(
+            "Refusing to publish sensitive files. Remove them, list them under `allow_sensitive` \
+             in fission.yaml or pass --allow-sensitive."
+        );
+    }
+    Ok(())
+}
+
+/// This function checks the files about to be uploaded for references to files that won't be,
+/// failing if there are any unless `skip_check` is set
+pub fn check_broken_links(
+    build_dir: &Path,
+    config: &AppConfig,
+    filter: &FileFilter,
+    options: &PublishOptions,
+) -> Result<()> {
+    if options.skip_check {
+        return Ok(());
+    }
+    let routing = Routing::load(&config.routing, build_dir)?;
+    let broken = check_links(build_dir, filter, &routing)?;
+    if broken.is_empty() {
+        return Ok(());
+    }
+    check::print(&broken);
+    bail!("Refusing to publish broken links. Fix them, or pass --skip-check to publish anyway.");
+}
+
+/// The filter that decides which files of the app get published
+pub fn file_filter(config: &AppConfig, include_hidden: bool) -> FileFilter {
+    let mut patterns = config.ignore.clone();
+    // The gateway doesn't read `_headers`, only `fission app serve` does
+    patterns.push(format!("/{}", HEADERS_FILE));
+    FileFilter::new(patterns, include_hidden)
+}
+
+/// Picks the CID of `path` itself out of the hashes `Ipfs::add` returned for it
+pub fn root_cid(path: &Path, hashes: &HashMap<String, String>) -> Result<String> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Cannot publish {:?}", path))?;
+    hashes
+        .get(name)
+        .cloned()
+        .with_context(|| format!("IPFS did not return a CID for {:?}", path))
+}
@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::ipfs::cid::Cid;
+use crate::ipfs::daemon::IpfsDaemon;
+use crate::ipfs::gateway::Gateway;
+use crate::ipfs::unixfs::{decode_block, BlockSource, Node, NodeType};
+
+/// What a pull wrote to disk
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PullStats {
+    pub files: usize,
+    pub bytes: u64,
+    /// How many blocks were fetched and verified
+    pub blocks: usize,
+}
+
+/// This function writes the UnixFS tree with the given CID to `dest`, which must not exist or be
+/// empty. Every block is checked against its CID before it is used, so the source doesn't need
+/// to be trusted.
+pub fn pull<B: BlockSource>(source: &B, cid: &Cid, dest: &Path) -> Result<PullStats> {
+    check_dest(dest)?;
+    let mut puller = Puller {
+        source,
+        stats: PullStats::default(),
+    };
+    puller.write_entry(cid, dest)?;
+    Ok(puller.stats)
+}
+
+/// This function pulls through the local IPFS daemon, falling back to the gateway if the daemon
+/// can't be launched or reached, or fails to fetch a block.
+pub fn pull_with_fallback(
+    daemon: &IpfsDaemon,
+    gateway: &str,
+    cid: &Cid,
+    dest: &Path,
+) -> Result<PullStats> {
+    let fallback = || -> Result<Gateway> {
+        let gateway = Gateway::new(gateway)?;
+        println!("Falling back to {}...", gateway.base_url());
+        Ok(gateway)
+    };
+    match block_on(daemon.launch()) {
+        Ok(()) => {
+            let res = pull_or_else(daemon, fallback, cid, dest);
+            if !daemon.is_stopping() {
+                daemon.shutdown()?;
+            }
+            res
+        }
+        Err(e) => {
+            eprintln!("{} {:#}", "Couldn't use IPFS:".yellow(), e);
+            pull(&fallback()?, cid, dest)
+        }
+    }
+}
+
+/// This function pulls from `source`, and if that fails, removes whatever it wrote and pulls
+/// from the source `fallback` returns instead
+pub fn pull_or_else<A, B, F>(source: &A, fallback: F, cid: &Cid, dest: &Path) -> Result<PullStats>
+where
+    A: BlockSource,
+    B: BlockSource,
+    F: FnOnce() -> Result<B>,
+{
+    check_dest(dest)?;
+    let existed = dest.is_dir();
+    match pull(source, cid, dest) {
+        Ok(stats) => Ok(stats),
+        Err(e) => {
+            eprintln!("{} {:#}", "Couldn't pull through IPFS:".yellow(), e);
+            if dest.is_dir() {
+                std::fs::remove_dir_all(dest)
+                    .with_context(|| format!("Failed to clean up {:?}", dest))?;
+            } else if dest.is_file() {
+                std::fs::remove_file(dest)
+                    .with_context(|| format!("Failed to clean up {:?}", dest))?;
+            }
+            if existed {
+                create_dir(dest)?;
+            }
+            pull(&fallback()?, cid, dest)
+        }
+    }
+}
+
+fn check_dest(dest: &Path) -> Result<()> {
+    if dest.is_file() || (dest.is_dir() && dest.read_dir()?.next().is_some()) {
+        bail!("{:?} already exists and is not an empty directory", dest);
+    }
+    Ok(())
+}
+
+struct Puller<'a, B> {
+    source: &'a B,
+    stats: PullStats,
+}
+
+impl<B: BlockSource> Puller<'_, B> {
+    fn fetch(&mut self, cid: &Cid) -> Result<Node> {
+        let block = self.source.fetch_block(cid)?;
+        cid.verify(&block)?;
+        self.stats.blocks += 1;
+        decode_block(cid, &block)
+    }
+
+    fn write_entry(&mut self, cid: &Cid, path: &Path) -> Result<()> {
+        let node = self.fetch(cid)?;
+        match node.node_type {
+            NodeType::Directory => {
+                create_dir(path)?;
+                for link in node.links {
+                    self.write_entry(&link.cid, &path.join(safe_name(&link.name)?))?;
+                }
+            }
+            NodeType::HamtShard => {
+                create_dir(path)?;
+                self.write_shard(node, path)?;
+            }
+            NodeType::Raw | NodeType::File => {
+                let mut file =
+                    File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+                self.write_file(node, &mut file)?;
+                self.stats.files += 1;
+            }
+            NodeType::Symlink => eprintln!("{} {:?}", "Skipping symlink".yellow(), path),
+            NodeType::Metadata => bail!("Unexpected UnixFS metadata node {}", cid),
+        }
+        Ok(())
+    }
+
+    /// Writes the contents of a file node and, in order, of every node it links to
+    fn write_file(&mut self, node: Node, out: &mut File) -> Result<()> {
+        out.write_all(&node.data)?;
+        self.stats.bytes += node.data.len() as u64;
+        for link in node.links {
+            let child = self.fetch(&link.cid)?;
+            self.write_file(child, out)?;
+        }
+        Ok(())
+    }
+
+    /// Sharded directories prefix every entry with two hex digits for the bucket it is in. Links
+    /// with nothing after the prefix are nested shards of the same directory.
+    fn write_shard(&mut self, node: Node, dir: &Path) -> Result<()> {
+        for link in node.links {
+            match link.name.get(2..) {
+                Some(name) if !name.is_empty() => {
+                    self.write_entry(&link.cid, &dir.join(safe_name(name)?))?
+                }
+                _ => {
+                    let child = self.fetch(&link.cid)?;
+                    self.write_shard(child, dir)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path).with_context(|| format!("Failed to create {:?}", path))
+}
+
+/// Makes sure a name from the DAG can't point outside the directory it is written to
+fn safe_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        bail!("Refusing to write an entry named {:?}", name);
+    }
+    Ok(name)
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+
+use crate::app::assets::AssetsConfig;
+use crate::app::config::{AppConfig, BuildConfig, PreviewConfig};
+use crate::app::detect::detect;
+use crate::app::routing::RoutingConfig;
+use crate::server::FissionServer;
+use crate::utils::config::APP_CONFIG_VERSION;
+
+pub struct RegisterOptions {
+    /// The build directory, relative to the app directory. Detected if not given.
+    pub build_dir: Option<PathBuf>,
+    /// The subdomain to ask for. Derived from the app directory's name if not given.
+    pub name: Option<String>,
+}
+
+/// This function creates an app on the server for the directory `app_dir` and writes its config
+/// there. The build directory and command are detected from the files in `app_dir`, unless a
+/// build directory is given.
+pub fn register<S: FissionServer + Sync>(
+    server: &S,
+    app_dir: &Path,
+    options: &RegisterOptions,
+) -> Result<AppConfig> {
+    if let Some((path, _)) = AppConfig::find(app_dir)? {
+        bail!("{:?} is already registered, see {:?}", app_dir, path);
+    }
+
+    let detected = detect(app_dir);
+    let build = match (&options.build_dir, detected) {
+        (Some(dir), _) => BuildConfig::dir(dir),
+        (None, Some(detected)) => {
+            println!(
+                "🔍 Detected {}, publishing {}",
+                detected.framework.to_string().bright_blue(),
+                detected.build_dir.display()
+            );
+            BuildConfig {
+                dir: detected.build_dir,
+                command: detected.build_command,
+                ..BuildConfig::default()
+            }
+        }
+        (None, None) => bail!(
+            "Couldn't tell where the app's files are built to. Pass it with --build-dir, such as \
+             `--build-dir ./dist`."
+        ),
+    };
+
+    let name = match &options.name {
+        Some(name) => {
+            check_name(name)?;
+            Some(name.clone())
+        }
+        None => suggest_name(app_dir),
+    };
+    let url = block_on(server.create_app(name.as_deref()))?;
+
+    let config = AppConfig {
+        version: APP_CONFIG_VERSION,
+        url,
+        build,
+        ignore: vec![],
+        allow_sensitive: vec![],
+        environments: BTreeMap::new(),
+        previews: PreviewConfig::default(),
+        routing: RoutingConfig::default(),
+        assets: AssetsConfig::default(),
+    };
+    config.save(app_dir)?;
+    Ok(config)
+}
+
+/// Checks that `name` can be used as a subdomain: 1 to 63 lowercase letters, digits and hyphens,
+/// not starting or ending with a hyphen
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        bail!(
+            "{:?} is not a valid app name. Use up to 63 lowercase letters, digits and hyphens, \
+             not starting or ending with a hyphen.",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Turns the name of the app directory into a subdomain, such as `My App` into `my-app`. Returns
+/// `None` if nothing usable is left, in which case the server picks a name.
+pub fn suggest_name(app_dir: &Path) -> Option<String> {
+    let dir = std::fs::canonicalize(app_dir).ok()?;
+    let mut name = String::new();
+    for c in dir.file_name()?.to_string_lossy().chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name: String = name.chars().take(63).collect();
+    let name = name.trim_end_matches('-').to_string();
+    check_name(&name).ok().map(|_| name)
+}
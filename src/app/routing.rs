@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::Issue;
+use crate::utils::config::{HEADERS_FILE, REDIRECTS_FILE};
+
+/// The statuses a `_redirects` rule can have. 200 rewrites, 3xx redirect and 4xx answer with the
+/// contents of the target.
+const REDIRECT_STATUSES: &[u16] = &[200, 301, 302, 303, 307, 308, 404, 410, 451];
+
+/// A rule of a `_redirects` file, such as `/blog/* /posts/:splat 301`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+fn default_status() -> u16 {
+    301
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.from, self.to, self.status)
+    }
+}
+
+impl Redirect {
+    /// This method returns where `path` goes if the rule matches it, with the placeholders and
+    /// the splat of `from` filled into `to`
+    pub fn apply(&self, path: &str) -> Option<String> {
+        let params = match_pattern(&self.from, path)?;
+        let mut names: Vec<_> = params.keys().collect();
+        // Longest first, so `:id` doesn't replace the start of `:idx`
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let mut to = self.to.clone();
+        for name in names {
+            to = to.replace(&format!(":{}", name), &params[name]);
+        }
+        Some(to)
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.status)
+    }
+
+    /// This method checks that the gateway can follow the rule
+    pub fn check(&self) -> Result<(), String> {
+        check_pattern(&self.from)?;
+        if self.from.contains('?') {
+            return Err(format!(
+                "{} matches on query parameters, which the gateway doesn't support",
+                self.from
+            ));
+        }
+        if !REDIRECT_STATUSES.contains(&self.status) {
+            return Err(format!("{} is not a status a rule can have", self.status));
+        }
+        let external = self.to.starts_with("http://") || self.to.starts_with("https://");
+        if !self.to.starts_with('/') && !external {
+            return Err(format!("{} is neither a path nor a URL", self.to));
+        }
+        if external && !self.is_redirect() {
+            return Err(format!(
+                "only redirects can point at other sites, not {}",
+                self.status
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A block of a `_headers` file: the headers added to responses for paths matching `path`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderRule {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// Redirect rules, SPA fallback and response headers from the app config. The rules and the
+/// fallback are published as the `_redirects` file at the root of the app. The gateway doesn't
+/// read `_headers` files, so the headers only apply under `fission app serve`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// Serve `/index.html` for paths nothing else matches, so deep links into a single-page app
+    /// work
+    #[serde(default)]
+    pub spa: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<Redirect>,
+    /// The headers `fission app serve` adds to responses, by path pattern
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl RoutingConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether there is anything to publish in `_redirects`
+    pub fn has_redirects(&self) -> bool {
+        self.spa || !self.redirects.is_empty()
+    }
+}
+
+/// Every rule the gateway follows for an app, in the order it tries them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Routing {
+    pub redirects: Vec<Redirect>,
+    pub headers: Vec<HeaderRule>,
+}
+
+impl Routing {
+    /// This function combines the rules of the app config with the contents of the app's own
+    /// `_redirects` and `_headers` files. Rules from the config come first, so they win, and the
+    /// SPA fallback comes last, so it only catches what nothing else does.
+    pub fn new(
+        config: &RoutingConfig,
+        redirects_file: Option<&str>,
+        headers_file: Option<&str>,
+    ) -> Result<Self> {
+        let mut redirects = config.redirects.clone();
+        if let Some(contents) = redirects_file {
+            redirects.extend(
+                parse_redirects(contents)
+                    .map_err(|issue| anyhow!("Invalid {} file, {}", REDIRECTS_FILE, issue))?,
+            );
+        }
+        if config.spa {
+            redirects.push(Redirect {
+                from: "/*".to_string(),
+                to: "/index.html".to_string(),
+                status: 200,
+            });
+        }
+        let mut headers: Vec<_> = config
+            .headers
+            .iter()
+            .map(|(path, headers)| HeaderRule {
+                path: path.clone(),
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+            })
+            .collect();
+        if let Some(contents) = headers_file {
+            headers.extend(
+                parse_headers(contents)
+                    .map_err(|issue| anyhow!("Invalid {} file, {}", HEADERS_FILE, issue))?,
+            );
+        }
+        Ok(Self { redirects, headers })
+    }
+
+    /// This function reads the `_redirects` and `_headers` files of the build directory, if it
+    /// has them, and combines them with the rules of the app config
+    pub fn load(config: &RoutingConfig, build_dir: &Path) -> Result<Self> {
+        let read = |name: &str| -> Result<Option<String>> {
+            let path = build_dir.join(name);
+            if !path.is_file() {
+                return Ok(None);
+            }
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            Ok(Some(contents))
+        };
+        Self::new(
+            config,
+            read(REDIRECTS_FILE)?.as_deref(),
+            read(HEADERS_FILE)?.as_deref(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.redirects.is_empty() && self.headers.is_empty()
+    }
+
+    /// This method returns the rules as a `_redirects` file
+    pub fn redirects_file(&self) -> String {
+        self.redirects
+            .iter()
+            .map(|rule| format!("{}\n", rule))
+            .collect()
+    }
+}
+
+/// This function parses a `_redirects` file, with one `from to [status]` rule per line. Rules
+/// are tried in order and the status defaults to 301.
+pub fn parse_redirects(contents: &str) -> Result<Vec<Redirect>, Issue> {
+    let mut rules = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let issue = |message: String| Issue {
+            line: Some(i + 1),
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (from, to, status) = match fields[..] {
+            [from, to] => (from, to, "301"),
+            [from, to, status] => (from, to, status),
+            _ => return Err(issue("expected `from to [status]`".to_string())),
+        };
+        if status.ends_with('!') {
+            return Err(issue(format!(
+                "forced rules such as {} aren't supported",
+                status
+            )));
+        }
+        let status = status
+            .parse::<u16>()
+            .map_err(|_| issue(format!("{} is not a status a rule can have", status)))?;
+        let rule = Redirect {
+            from: from.to_string(),
+            to: to.to_string(),
+            status,
+        };
+        rule.check().map_err(issue)?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// This function parses a `_headers` file, where each path is followed by the `Name: value`
+/// headers for it, indented
+pub fn parse_headers(contents: &str) -> Result<Vec<HeaderRule>, Issue> {
+    let mut rules: Vec<HeaderRule> = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let issue = |message: String| Issue {
+            line: Some(i + 1),
+            message,
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            check_pattern(trimmed).map_err(issue)?;
+            rules.push(HeaderRule {
+                path: trimmed.to_string(),
+                headers: vec![],
+            });
+            continue;
+        }
+        let rule = match rules.last_mut() {
+            Some(rule) => rule,
+            None => {
+                return Err(issue(
+                    "headers have to follow the path they are for".to_string(),
+                ))
+            }
+        };
+        let (name, value) = match trimmed.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => return Err(issue(format!("expected `Name: value`, not {:?}", trimmed))),
+        };
+        check_header_name(name).map_err(issue)?;
+        rule.headers.push((name.to_string(), value.to_string()));
+    }
+    Ok(rules)
+}
+
+/// This function returns the headers of every rule matching `path`, in the order of the file
+pub fn headers_for(rules: &[HeaderRule], path: &str) -> Vec<(String, String)> {
+    rules
+        .iter()
+        .filter(|rule| match_pattern(&rule.path, path).is_some())
+        .flat_map(|rule| rule.headers.iter().cloned())
+        .collect()
+}
+
+/// This function matches `path` against a pattern such as `/posts/:year/*`, returning the
+/// values of the placeholders, with whatever `*` matched as `splat`. Trailing slashes don't
+/// matter.
+pub fn match_pattern(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut segments = trim_slash(path).split('/');
+    for part in trim_slash(pattern).split('/') {
+        if part == "*" {
+            let rest: Vec<_> = segments.collect();
+            params.insert("splat".to_string(), rest.join("/"));
+            return Some(params);
+        }
+        let segment = segments.next()?;
+        match part.strip_prefix(':') {
+            Some(name) if !segment.is_empty() => {
+                params.insert(name.to_string(), segment.to_string());
+            }
+            Some(_) => return None,
+            None if part == segment => {}
+            None => return None,
+        }
+    }
+    match segments.next() {
+        Some(_) => None,
+        None => Some(params),
+    }
+}
+
+fn trim_slash(path: &str) -> &str {
+    if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    }
+}
+
+pub fn check_header_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+    if !valid {
+        return Err(format!("{:?} is not a valid header name", name));
+    }
+    Ok(())
+}
+
+/// This function checks that a pattern is a path, with `*` only at its end
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("{} has to start with /", pattern));
+    }
+    let parts: Vec<_> = pattern.split('/').collect();
+    let last = parts.len() - 1;
+    if parts[..last].iter().any(|part| part.contains('*'))
+        || (parts[last].contains('*') && parts[last] != "*")
+    {
+        return Err(format!("{} can only end with *", pattern));
+    }
+    Ok(())
+}
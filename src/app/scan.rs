@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ignore::Match;
+
+use crate::utils::config::SCAN_MAX_FILE_SIZE;
+use crate::utils::file_filter::{build_matcher, FileFilter};
+use crate::utils::file_management::format_size;
+
+/// A file that looks like it shouldn't be published
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// The path relative to the build directory
+    pub path: String,
+    pub reason: String,
+}
+
+/// File names that usually hold credentials
+const SENSITIVE_NAMES: &[&str] = &[
+    ".env",
+    ".npmrc",
+    ".pypirc",
+    ".netrc",
+    ".htpasswd",
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "credentials.json",
+];
+
+/// Extensions of key and certificate stores
+const SENSITIVE_EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx", "jks", "keystore"];
+
+/// A token format, as (description, prefix, allowed characters, minimum length after the prefix)
+type TokenPattern = (&'static str, &'static str, fn(char) -> bool, usize);
+
+const TOKEN_PATTERNS: &[TokenPattern] = &[
+    ("an AWS access key", "AKIA", is_upper_alphanumeric, 16),
+    ("a GitHub token", "ghp_", is_alphanumeric, 36),
+    ("a GitHub token", "gho_", is_alphanumeric, 36),
+    ("a GitHub token", "ghu_", is_alphanumeric, 36),
+    ("a GitHub token", "ghs_", is_alphanumeric, 36),
+    ("a GitHub token", "github_pat_", is_word, 22),
+    ("a Slack token", "xoxb-", is_slack, 10),
+    ("a Slack token", "xoxp-", is_slack, 10),
+    ("a Slack token", "xoxa-", is_slack, 10),
+    ("a Stripe secret key", "sk_live_", is_alphanumeric, 24),
+    ("a Google API key", "AIza", is_url_safe, 35),
+];
+
+/// This function looks through the files the filter lets through for private keys, tokens,
+/// credential files and files too large to publish. Files matching one of the gitignore style
+/// `allowlist` patterns are skipped.
+pub fn scan(build_dir: &Path, filter: &FileFilter, allowlist: &[String]) -> Result<Vec<Finding>> {
+    let allowed = build_matcher(build_dir, allowlist)?;
+
+    let mut findings = vec![];
+    for entry in filter.walk(build_dir)? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Match::Ignore(_) = allowed.matched_path_or_any_parents(path, false) {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(build_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        let size = path
+            .metadata()
+            .with_context(|| format!("Failed to read {:?}", path))?
+            .len();
+        // Large files are reported without reading them
+        let reason = match check_name(path) {
+            Some(reason) => Some(reason),
+            None if size > SCAN_MAX_FILE_SIZE => Some(format!(
+                "is larger than {}",
+                format_size(SCAN_MAX_FILE_SIZE)
+            )),
+            None => {
+                let data =
+                    std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+                check_contents(&String::from_utf8_lossy(&data))
+            }
+        };
+        if let Some(reason) = reason {
+            findings.push(Finding {
+                path: relative,
+                reason,
+            });
+        }
+    }
+    findings.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(findings)
+}
+
+/// Returns why a single file looks sensitive, if it does
+pub fn check_file(path: &Path, data: &[u8]) -> Option<String> {
+    check_name(path).or_else(|| check_contents(&String::from_utf8_lossy(data)))
+}
+
+/// Returns why the name of a file suggests it holds credentials, if it does
+fn check_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    if SENSITIVE_NAMES.contains(&name.as_ref()) || name.starts_with(".env.") {
+        return Some("is usually a credentials file".to_string());
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    if let Some(extension) = extension {
+        if SENSITIVE_EXTENSIONS.contains(&extension.as_str()) {
+            return Some(format!("is a .{} key or certificate file", extension));
+        }
+    }
+    None
+}
+
+/// Returns what kind of secret the text contains, if any
+pub fn check_contents(text: &str) -> Option<String> {
+    for line in text.lines() {
+        if line.contains("-----BEGIN") && line.contains("PRIVATE KEY-----") {
+            return Some("contains a private key".to_string());
+        }
+        // `fission generate credentials` prints keys as 32 bytes of base64. Only lines holding
+        // nothing else are checked, since hashes such as SRI integrity attributes look the same.
+        if is_ed25519_key(line.trim().trim_matches(|c| c == '"' || c == '\'')) {
+            return Some("contains what looks like an Ed25519 private key".to_string());
+        }
+        for (description, prefix, allowed, min_len) in TOKEN_PATTERNS {
+            if contains_token(line, prefix, *allowed, *min_len) {
+                return Some(format!("contains what looks like {}", description));
+            }
+        }
+    }
+    None
+}
+
+fn is_ed25519_key(s: &str) -> bool {
+    s.len() == 44 && s.ends_with('=') && matches!(base64::decode(s), Ok(bytes) if bytes.len() == 32)
+}
+
+/// Whether `prefix` appears at the start of a word and is followed by at least `min_len`
+/// allowed characters
+fn contains_token(line: &str, prefix: &str, allowed: fn(char) -> bool, min_len: usize) -> bool {
+    line.match_indices(prefix).any(|(start, _)| {
+        let at_word_start =
+            !matches!(line[..start].chars().last(), Some(c) if c.is_ascii_alphanumeric());
+        at_word_start
+            && line[start + prefix.len()..]
+                .chars()
+                .take_while(|c| allowed(*c))
+                .count()
+                >= min_len
+    })
+}
+
+fn is_alphanumeric(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+fn is_upper_alphanumeric(c: char) -> bool {
+    c.is_ascii_uppercase() || c.is_ascii_digit()
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_slack(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+fn is_url_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
@@ -0,0 +1,391 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+use hyper::header::HOST;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::percent_decode_str;
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+
+use crate::app::routing::{headers_for, Redirect, Routing, RoutingConfig};
+use crate::ipfs::cid::Cid;
+use crate::ipfs::unixfs::read_file;
+use crate::ipfs::Ipfs;
+use crate::utils::config::{HEADERS_FILE, REDIRECTS_FILE, WATCH_POLL_MS};
+
+/// What a path of a site is
+pub enum Entry {
+    Dir,
+    File(Vec<u8>),
+}
+
+/// A tree of files to serve, on disk or on IPFS
+pub trait Site {
+    /// This method looks up `path`, relative to the root and separated by `/`, reading it if it
+    /// is a file. The root itself is the empty path.
+    fn lookup(&self, path: &str) -> Result<Option<Entry>>;
+}
+
+/// A build directory on disk, read afresh on every request
+pub struct DirSite {
+    pub root: PathBuf,
+}
+
+impl Site for DirSite {
+    fn lookup(&self, path: &str) -> Result<Option<Entry>> {
+        let path = self.root.join(path);
+        if path.is_dir() {
+            Ok(Some(Entry::Dir))
+        } else if path.is_file() {
+            let contents =
+                std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+            Ok(Some(Entry::File(contents)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A UnixFS directory, read through IPFS
+pub struct IpfsSite<'a, I> {
+    ipfs: &'a I,
+    cid: Cid,
+}
+
+impl<'a, I: Ipfs + Sync> IpfsSite<'a, I> {
+    pub fn new(ipfs: &'a I, cid: Cid) -> Self {
+        Self { ipfs, cid }
+    }
+}
+
+impl<I: Ipfs + Sync> Site for IpfsSite<'_, I> {
+    fn lookup(&self, path: &str) -> Result<Option<Entry>> {
+        let mut cid = self.cid.to_string();
+        let mut is_dir = true;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if !is_dir {
+                return Ok(None);
+            }
+            let link = block_on(self.ipfs.ls(&cid))?
+                .into_iter()
+                .find(|link| link.name == name);
+            match link {
+                Some(link) => {
+                    cid = link.cid;
+                    is_dir = link.is_dir;
+                }
+                None => return Ok(None),
+            }
+        }
+        if is_dir {
+            return Ok(Some(Entry::Dir));
+        }
+        let cid = cid.parse()?;
+        Ok(Some(Entry::File(read_file(self.ipfs, &cid)?)))
+    }
+}
+
+/// A response, before it is handed to hyper
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SiteResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl SiteResponse {
+    fn text(status: u16, text: &str) -> Self {
+        Self {
+            status,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/plain; charset=utf-8".to_string(),
+            )],
+            body: text.as_bytes().to_vec(),
+        }
+    }
+
+    fn redirect(status: u16, location: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("Location".to_string(), location.to_string())],
+            body: vec![],
+        }
+    }
+
+    fn file(status: u16, path: &str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type(path).to_string())],
+            body,
+        }
+    }
+}
+
+pub struct ServeOptions {
+    pub port: u16,
+    /// The host the site is served at, such as `my-app.localhost`. Like on the gateway, every
+    /// site gets its own subdomain, so they don't share an origin.
+    pub host: String,
+    /// The rules from the app config, which publishing would add to those of the site
+    pub routing: RoutingConfig,
+}
+
+/// A request, on its way from hyper to the thread serving the site
+struct Incoming {
+    host: Option<String>,
+    path: String,
+    reply: oneshot::Sender<SiteResponse>,
+}
+
+/// This function serves `site` on localhost until `stop` returns true. Requests are answered on
+/// the calling thread, one at a time, since reading from IPFS blocks on its own runtime.
+pub fn serve<S, F>(site: &S, options: &ServeOptions, stop: F) -> Result<()>
+where
+    S: Site,
+    F: Fn() -> bool,
+{
+    let tokio = Runtime::new()?;
+    let (tx, rx) = channel();
+    let addr = SocketAddr::from(([127, 0, 0, 1], options.port));
+    let server = {
+        let _guard = tokio.enter();
+        Server::try_bind(&addr).with_context(|| format!("Failed to listen on port {}", addr))?
+    };
+    let make_service = make_service_fn(move |_| {
+        let tx = tx.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| forward(tx.clone(), req))) }
+    });
+    tokio.spawn(server.serve(make_service));
+    println!(
+        "🌐 Serving at {}",
+        format!("http://{}:{}", options.host, options.port).bright_blue()
+    );
+
+    while !stop() {
+        let incoming: Incoming = match rx.recv_timeout(Duration::from_millis(WATCH_POLL_MS)) {
+            Ok(incoming) => incoming,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let res = handle(site, options, incoming.host.as_deref(), &incoming.path);
+        let status = match res.status {
+            200..=299 => res.status.to_string().green(),
+            300..=399 => res.status.to_string().blue(),
+            _ => res.status.to_string().red(),
+        };
+        println!("{} {}", status, incoming.path);
+        // The client may have gone already
+        let _ = incoming.reply.send(res);
+    }
+    Ok(())
+}
+
+/// Hands a request over to the thread serving the site and waits for its answer
+async fn forward(tx: Sender<Incoming>, req: Request<Body>) -> hyper::http::Result<Response<Body>> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty());
+    }
+    let (reply, answer) = oneshot::channel();
+    let incoming = Incoming {
+        host: req
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .map(str::to_string),
+        path: req.uri().path().to_string(),
+        reply,
+    };
+    let res = match tx.send(incoming) {
+        Ok(()) => answer.await.ok(),
+        Err(_) => None,
+    };
+    // Only while shutting down
+    let res = match res {
+        Some(res) => res,
+        None => {
+            return Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::empty())
+        }
+    };
+    let mut builder = Response::builder().status(res.status);
+    for (name, value) in &res.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder.body(Body::from(res.body))
+}
+
+/// Redirects requests for other hosts to the site's subdomain, dropping a `/ipfs/<cid>` prefix
+/// the way subdomain gateways do
+fn handle<S: Site>(
+    site: &S,
+    options: &ServeOptions,
+    host: Option<&str>,
+    path: &str,
+) -> SiteResponse {
+    let expected = format!("{}:{}", options.host, options.port);
+    if host != Some(expected.as_str()) {
+        let path = match path.strip_prefix("/ipfs/") {
+            Some(rest) => rest.find('/').map(|i| &rest[i..]).unwrap_or("/"),
+            None => path,
+        };
+        return SiteResponse::redirect(301, &format!("http://{}{}", expected, path));
+    }
+    match respond(site, path, &options.routing) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("💥 Failed to serve {}.\n{:#}", path, e);
+            SiteResponse::text(500, &format!("{:#}", e))
+        }
+    }
+}
+
+/// This function answers a request for `path` the way the gateway would: files are served as
+/// they are, directories by their `index.html`, and anything else goes through the redirect
+/// rules before falling back to the closest `404.html`. The rules of `routing` come before those
+/// of the site's own `_redirects` and `_headers` files, as they do once published.
+pub fn respond<S: Site>(site: &S, path: &str, routing: &RoutingConfig) -> Result<SiteResponse> {
+    let path = match percent_decode_str(path).decode_utf8() {
+        Ok(path) if !path.split('/').any(|segment| segment == "..") => path.to_string(),
+        _ => return Ok(SiteResponse::text(400, "Bad request")),
+    };
+    let routing = Routing::new(
+        routing,
+        read_text(site, REDIRECTS_FILE)?.as_deref(),
+        read_text(site, HEADERS_FILE)?.as_deref(),
+    )?;
+    let mut res = route(site, &path, &routing.redirects)?;
+    res.headers.extend(headers_for(&routing.headers, &path));
+    Ok(res)
+}
+
+fn route<S: Site>(site: &S, path: &str, redirects: &[Redirect]) -> Result<SiteResponse> {
+    let relative = path.trim_start_matches('/');
+    if let Some(Entry::Dir) = site.lookup(relative)? {
+        if !path.ends_with('/') {
+            return Ok(SiteResponse::redirect(301, &format!("{}/", path)));
+        }
+    }
+    if let Some((served, body)) = resolve(site, relative)? {
+        return Ok(SiteResponse::file(200, &served, body));
+    }
+
+    for rule in redirects {
+        let to = match rule.apply(path) {
+            Some(to) => to,
+            None => continue,
+        };
+        if rule.is_redirect() {
+            return Ok(SiteResponse::redirect(rule.status, &to));
+        }
+        if let Some((served, body)) = resolve(site, to.trim_start_matches('/'))? {
+            return Ok(SiteResponse::file(rule.status, &served, body));
+        }
+        break;
+    }
+
+    // The closest 404.html, looking in every directory up to the root
+    let mut dir = relative;
+    loop {
+        dir = match dir.rfind('/') {
+            Some(i) => &dir[..i],
+            None => "",
+        };
+        let not_found = join(dir, "404.html");
+        if let Some(Entry::File(body)) = site.lookup(&not_found)? {
+            return Ok(SiteResponse::file(404, &not_found, body));
+        }
+        if dir.is_empty() {
+            return Ok(SiteResponse::text(404, "Not found"));
+        }
+    }
+}
+
+/// Finds the file `path` stands for, which is its `index.html` for directories
+fn resolve<S: Site>(site: &S, path: &str) -> Result<Option<(String, Vec<u8>)>> {
+    match site.lookup(path)? {
+        Some(Entry::File(body)) => Ok(Some((path.to_string(), body))),
+        Some(Entry::Dir) => {
+            let index = join(path.trim_end_matches('/'), "index.html");
+            match site.lookup(&index)? {
+                Some(Entry::File(body)) => Ok(Some((index, body))),
+                _ => Ok(None),
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_text<S: Site>(site: &S, path: &str) -> Result<Option<String>> {
+    match site.lookup(path)? {
+        Some(Entry::File(contents)) => Ok(Some(
+            String::from_utf8(contents).with_context(|| format!("{} is not UTF-8", path))?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// This function returns the MIME type the gateway serves a file with, based on its extension
+pub fn content_type(path: &str) -> &'static str {
+    let extension = match path.rsplit_once('.') {
+        Some((_, extension)) if !extension.contains('/') => extension.to_ascii_lowercase(),
+        _ => return "application/octet-stream",
+    };
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// This function returns the host a CID is served at, which takes a base32 CIDv1 since hosts
+/// are case insensitive
+pub fn cid_host(cid: &Cid) -> String {
+    let mut cid = cid.clone();
+    cid.version = 1;
+    format!("{}.ipfs.localhost", cid)
+}
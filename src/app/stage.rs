@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::app::assets;
+use crate::app::config::AppConfig;
+use crate::app::routing::Routing;
+use crate::utils::config::{APP_STATE_DIR, REDIRECTS_FILE, STAGE_DIR};
+use crate::utils::file_filter::FileFilter;
+
+/// This function returns the directory to upload for the app. That is the build directory
+/// itself, unless the app config adds redirect rules or turns on the asset pipeline: then it is
+/// a copy of the build directory in `.fission/stage` with the rules written to its `_redirects`
+/// file and the pipeline run over it. The copy keeps the build directory's name, so `root_cid`
+/// finds it. `filter` leaves `_headers` out, as only `fission app serve` applies headers. The
+/// `_redirects` and `_headers` files are checked either way, as the gateway would ignore a broken
+/// `_redirects` and `fission app serve` a broken `_headers`.
+pub fn stage(
+    app_dir: &Path,
+    config: &AppConfig,
+    build_dir: &Path,
+    filter: &FileFilter,
+) -> Result<PathBuf> {
+    let stage_root = app_dir.join(APP_STATE_DIR).join(STAGE_DIR);
+    stage_in(&stage_root, config, build_dir, filter)
+}
+
+/// Like `stage`, but copies the build directory under `stage_root` rather than `.fission/stage`
+pub fn stage_in(
+    stage_root: &Path,
+    config: &AppConfig,
+    build_dir: &Path,
+    filter: &FileFilter,
+) -> Result<PathBuf> {
+    let routing = Routing::load(&config.routing, build_dir)?;
+    if !config.routing.has_redirects() && !config.assets.is_enabled() {
+        return Ok(build_dir.to_path_buf());
+    }
+
+    let name = build_dir
+        .file_name()
+        .with_context(|| format!("Cannot publish {:?}", build_dir))?;
+    let stage_dir = stage_root.join(name);
+    if stage_dir.exists() {
+        std::fs::remove_dir_all(&stage_dir)
+            .with_context(|| format!("Failed to clear {:?}", stage_dir))?;
+    }
+    for entry in filter.walk(build_dir)? {
+        let target = stage_dir.join(entry.path().strip_prefix(build_dir)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {:?}", target))?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {:?}", entry.path()))?;
+        }
+    }
+    let path = stage_dir.join(REDIRECTS_FILE);
+    let contents = routing.redirects_file();
+    if contents.is_empty() {
+        // The build directory's own file held nothing but comments
+        let _ = std::fs::remove_file(&path);
+    } else {
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+    }
+    if config.assets.is_enabled() {
+        assets::process(&stage_dir, &config.assets)?;
+    }
+    std::fs::canonicalize(&stage_dir).with_context(|| format!("Failed to stage {:?}", stage_dir))
+}
@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use futures::executor::block_on;
+use ignore::gitignore::Gitignore;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::app::build::run_build;
+use crate::app::config::AppConfig;
+use crate::app::history::{self, Deploy};
+use crate::app::manifest::{build_manifest, PublishState};
+use crate::app::publish::{
+    build_dir, check_broken_links, check_sensitive, file_filter, load_config, publish, root_cid,
+    PublishOptions,
+};
+use crate::app::stage::stage;
+use crate::ipfs::Ipfs;
+use crate::server::FissionServer;
+use crate::utils::config::{APP_STATE_DIR, WATCH_DEBOUNCE_MS, WATCH_POLL_MS};
+
+/// This function publishes the app in `app_dir`, then watches its build directory and republishes
+/// whenever the root CID changes. Apps with a build command are built again when their sources
+/// change, unless `skip_build` is set. It returns once `stop` returns true, which it checks
+/// between bursts of changes.
+pub fn watch<I, S, F>(
+    ipfs: &I,
+    server: &S,
+    app_dir: &Path,
+    options: &PublishOptions,
+    stop: F,
+) -> Result<()>
+where
+    I: Ipfs + Sync,
+    S: FissionServer + Sync,
+    F: Fn() -> bool,
+{
+    let mut cid = publish(ipfs, server, app_dir, options)?.cid;
+    let config = load_config(app_dir, options)?;
+    let build_dir = build_dir(app_dir, &config)?;
+
+    let sources = match config.build.command {
+        Some(_) if !options.skip_build => Some(std::fs::canonicalize(app_dir)?),
+        _ => None,
+    };
+    let watched = WatchedPaths::new(build_dir.clone(), sources);
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mut dirs = vec![&build_dir];
+    if let Some(sources) = &watched.sources {
+        dirs.retain(|dir| !dir.starts_with(sources));
+        dirs.push(sources);
+    }
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+        println!("👀 Watching {} for changes...", dir.display());
+    }
+
+    while !stop() {
+        let changes = next_batch(&rx, &watched)?;
+        if changes == Changes::None {
+            continue;
+        }
+        if changes == Changes::Sources {
+            if let Err(e) = run_build(app_dir, &config.build) {
+                // Keep watching, the next change may well fix it
+                eprintln!("💥 Failed to build.\n{:#}", e);
+                continue;
+            }
+        }
+        match republish(ipfs, server, app_dir, &config, &build_dir, &cid, options) {
+            Ok(Some(new_cid)) => {
+                println!("🚀 Published {} to {}", new_cid.bright_blue(), config.url);
+                cid = new_cid;
+            }
+            Ok(None) if options.verbose => println!("No changes to publish"),
+            Ok(None) => {}
+            // Keep watching, the next change may well fix it
+            Err(e) => eprintln!("💥 Failed to publish.\n{:#}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Uploads the build directory and points the app at it, unless its root CID is still `cid`.
+/// Returns the new CID if anything was published.
+fn republish<I, S>(
+    ipfs: &I,
+    server: &S,
+    app_dir: &Path,
+    config: &AppConfig,
+    build_dir: &Path,
+    cid: &str,
+    options: &PublishOptions,
+) -> Result<Option<String>>
+where
+    I: Ipfs + Sync,
+    S: FissionServer + Sync,
+{
+    let filter = file_filter(config, options.include_hidden);
+    let upload_dir = stage(app_dir, config, build_dir, &filter)?;
+    let new_cid = root_cid(&upload_dir, &block_on(ipfs.hash(&upload_dir, &filter))?)?;
+    if new_cid == cid {
+        return Ok(None);
+    }
+    let mut deploy = Deploy::now(app_dir, &config.url, &new_cid, server.did())?;
+    if options.update_data {
+        check_sensitive(build_dir, config, &filter, options)?;
+        check_broken_links(build_dir, config, &filter, options)?;
+        let files = build_manifest(&upload_dir, &filter)?;
+        deploy.size = Some(files.values().map(|file| file.size).sum());
+        block_on(ipfs.add(&upload_dir, &filter))?;
+        let state = PublishState {
+            url: config.url.clone(),
+            cid: new_cid.clone(),
+            files,
+        };
+        state.save(app_dir)?;
+    }
+    if options.update_dns {
+        block_on(server.update_app(&config.url, &new_cid, options.update_data))?;
+        history::append(app_dir, deploy)?;
+    }
+    Ok(Some(new_cid))
+}
+
+/// What a burst of file changes calls for, from least to most
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Changes {
+    None,
+    /// Files in the build directory changed, so the app may need to be published again
+    Built,
+    /// The app's sources changed, so it needs to be built first
+    Sources,
+}
+
+/// The directories `watch` follows changes in
+pub struct WatchedPaths {
+    build_dir: PathBuf,
+    /// The app directory, when changes to it should trigger a build
+    sources: Option<PathBuf>,
+    /// The app's `.gitignore`, which usually covers build output and caches the build writes
+    gitignore: Gitignore,
+}
+
+impl WatchedPaths {
+    /// This function takes absolute paths, as those are what file change events carry
+    pub fn new(build_dir: PathBuf, sources: Option<PathBuf>) -> Self {
+        let gitignore = match &sources {
+            Some(sources) => Gitignore::new(sources.join(".gitignore")).0,
+            None => Gitignore::empty(),
+        };
+        Self {
+            build_dir,
+            sources,
+            gitignore,
+        }
+    }
+
+    /// This method returns what a change to `path` calls for
+    pub fn classify(&self, path: &Path) -> Changes {
+        // Staging for a publish writes to the app's state directory, which is inside the build
+        // directory when the app is published as it is
+        let is_state = path.components().any(|c| c.as_os_str() == APP_STATE_DIR);
+        if is_state || is_temp_file(path) {
+            return Changes::None;
+        }
+        if path.starts_with(&self.build_dir) {
+            return Changes::Built;
+        }
+        let sources = match &self.sources {
+            Some(sources) => sources,
+            None => return Changes::None,
+        };
+        let relative = match path.strip_prefix(sources) {
+            Ok(relative) => relative,
+            Err(_) => return Changes::None,
+        };
+        // Dependencies and tools' caches change as the build runs, and would trigger it again
+        let is_generated = relative.components().any(|c| {
+            [".git", "node_modules"]
+                .iter()
+                .any(|dir| c.as_os_str() == *dir)
+        });
+        if is_generated
+            || self
+                .gitignore
+                .matched_path_or_any_parents(relative, path.is_dir())
+                .is_ignore()
+        {
+            return Changes::None;
+        }
+        Changes::Sources
+    }
+
+    fn classify_event(&self, event: &Event) -> Changes {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Changes::None;
+        }
+        event
+            .paths
+            .iter()
+            .map(|path| self.classify(path))
+            .max()
+            .unwrap_or(Changes::None)
+    }
+}
+
+/// Waits for the next burst of relevant changes and returns once it has been quiet for
+/// `WATCH_DEBOUNCE_MS`. Returns `Changes::None` if nothing changed within `WATCH_POLL_MS`, so the
+/// caller gets a chance to stop.
+fn next_batch(rx: &Receiver<notify::Result<Event>>, watched: &WatchedPaths) -> Result<Changes> {
+    let mut changes = Changes::None;
+    let mut timeout = Duration::from_millis(WATCH_POLL_MS);
+    loop {
+        match rx.recv_timeout(timeout) {
+            Ok(event) => {
+                let kind = watched.classify_event(&event?);
+                if kind != Changes::None {
+                    changes = changes.max(kind);
+                    timeout = Duration::from_millis(WATCH_DEBOUNCE_MS);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => return Ok(changes),
+            Err(RecvTimeoutError::Disconnected) => bail!("Stopped receiving file changes"),
+        }
+    }
+}
+
+/// This function returns whether the path looks like a swap, backup or lock file an editor
+/// writes next to the files being edited
+pub fn is_temp_file(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(x) => x,
+        None => return false,
+    };
+    name.ends_with('~')
+        || name.starts_with(".#")
+        || (name.starts_with('#') && name.ends_with('#'))
+        || [".swp", ".swo", ".swx", ".tmp", ".crswap"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        // Vim checks whether it can write to a directory by creating this file
+        || name == "4913"
+        || name == ".DS_Store"
+}
@@ -1,4 +1,5 @@
 pub mod app;
 pub mod generate;
+pub mod ipfs;
 pub mod setup;
 pub mod user;
@@ -1,7 +1,41 @@
+use crate::app::check::{self, check_links};
+use crate::app::config::{self, AppConfig};
+use crate::app::diff::AppDiff;
+use crate::app::domain;
+use crate::app::history::{self, rollback};
+use crate::app::info::info;
+use crate::app::manage::{confirm_destroy, destroy, list, print_list, rename, resolve_url};
+use crate::app::plan::plan;
+use crate::app::preview::{previews, print_previews, prune};
+use crate::app::publish::{build_dir, config_for, file_filter, publish, PublishOptions};
+use crate::app::pull::pull_with_fallback;
+use crate::app::register::{register, RegisterOptions};
+use crate::app::routing::{Routing, RoutingConfig};
+use crate::app::serve::{cid_host, serve, DirSite, IpfsSite, ServeOptions};
+use crate::app::watch::watch as watch_app;
+use crate::ipfs::cid::Cid;
+use crate::ipfs::daemon::IpfsDaemon;
+use crate::ipfs::peering::cluster_peers;
 use crate::legacy::{prepare_args, prepare_flags};
-use anyhow::Result;
-use clap::{ArgEnum, Args, Subcommand};
-use std::{collections::HashMap, process::Command};
+use crate::server::client::ServerClient;
+use crate::server::ucan::Potency;
+use crate::server::FissionServer;
+use crate::settings::Settings;
+use crate::utils::browser::open_in_browser;
+use crate::utils::config::{DNS_RESOLVER_URL, IPFS_GATEWAY_URL, SERVE_PORT};
+use crate::utils::dns::DohResolver;
+use crate::utils::file_filter::FileFilter;
+use crate::utils::file_management::format_size;
+use anyhow::{bail, Result};
+use clap::{builder::BoolishValueParser, ArgAction, Args, Subcommand};
+use colored::Colorize;
+use futures::executor::block_on;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
 
 #[derive(Args)]
 pub struct App {
@@ -9,8 +43,57 @@ pub struct App {
     command: AppCommands,
 }
 
+impl App {
+    /// This method returns the exit code for when the command fails. `app diff` exits with 1
+    /// when the app differs, like `diff` does, so its errors exit with 2 to tell the two apart.
+    pub fn error_code(&self) -> i32 {
+        match self.command {
+            AppCommands::Diff { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// How an app command that didn't fail ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    /// `app diff` found that the published app isn't the build directory
+    Differs,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Done => 0,
+            Outcome::Differs => 1,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum AppCommands {
+    #[clap(about = "Check the build directory for broken links")]
+    Check {
+        #[clap(help = "The directory of the app", default_value = "./")]
+        path: String,
+        #[clap(
+            long = "include-hidden",
+            help = "Count dot files that aren't explicitly ignored as published"
+        )]
+        include_hidden: bool,
+        #[clap(
+            long,
+            help = "Check the build directory of this environment",
+            value_name = "NAME"
+        )]
+        env: Option<String>,
+    },
+    #[clap(about = "Manage the app config")]
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
     #[clap(about = "Delegate capability to an audience DID")]
     Delegate {
         #[clap(short, long, value_name = "NAME", help = "The target app")]
@@ -39,13 +122,95 @@ pub enum AppCommands {
         #[clap(from_global)]
         remote: Option<String>,
     },
+    #[clap(about = "Delete an app for good")]
+    Destroy {
+        #[clap(help = "The name or URL of the app", value_name = "NAME")]
+        name: String,
+        #[clap(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Compare the build directory with the published app")]
+    Diff {
+        #[clap(help = "The directory of the app", default_value = "./")]
+        path: String,
+        #[clap(
+            long = "include-hidden",
+            help = "Compare dot files that aren't explicitly ignored"
+        )]
+        include_hidden: bool,
+        #[clap(
+            long,
+            help = "Compare the app of this environment",
+            value_name = "NAME"
+        )]
+        env: Option<String>,
+        #[clap(
+            long = "ipfs-bin",
+            help = "Path to IPFS binary [default: `which ipfs`]",
+            value_name = "BIN_PATH"
+        )]
+        ipfs_bin: Option<String>,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Manage the app's custom domains")]
+    Domain {
+        #[clap(subcommand)]
+        command: DomainCommands,
+    },
+    #[clap(about = "List what was published from this directory")]
+    History {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(
+            long,
+            help = "List the deploys of the app of this environment",
+            value_name = "NAME",
+            conflicts_with = "preview"
+        )]
+        env: Option<String>,
+        #[clap(
+            long,
+            help = "List the deploys of the preview app of this branch",
+            value_name = "BRANCH"
+        )]
+        preview: Option<String>,
+    },
     #[clap(about = "Detail about the current app")]
     Info {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(long, help = "Print the details as JSON")]
+        json: bool,
         #[clap(from_global)]
-        verbose: bool,
+        remote: Option<String>,
+    },
+    #[clap(about = "List your apps")]
+    List {
+        #[clap(long, help = "Print the apps as JSON")]
+        json: bool,
         #[clap(from_global)]
         remote: Option<String>,
     },
+    #[clap(about = "Manage the app's per-branch previews")]
+    Previews {
+        #[clap(subcommand)]
+        command: PreviewCommands,
+    },
     #[clap(about = "Upload the working directory")]
     Publish {
         #[clap(
@@ -55,8 +220,58 @@ pub enum AppCommands {
         path: String,
         #[clap(short, long, help = "Open your default browser after publish")]
         open: bool,
-        #[clap(short, long, help = "Watch for changes & automatically trigger upload")]
+        #[clap(
+            short,
+            long,
+            help = "Watch for changes & automatically trigger upload, running the build command \
+                    again when sources change"
+        )]
         watch: bool,
+        #[clap(
+            long = "dry-run",
+            help = "Show what would change without publishing",
+            conflicts_with = "watch"
+        )]
+        dry_run: bool,
+        #[clap(
+            long = "include-hidden",
+            help = "Publish dot files that aren't explicitly ignored"
+        )]
+        include_hidden: bool,
+        #[clap(
+            long = "allow-sensitive",
+            help = "Publish files that look like they contain secrets"
+        )]
+        allow_sensitive: bool,
+        #[clap(
+            short,
+            long,
+            help = "Upload even if nothing changed since the last publish"
+        )]
+        force: bool,
+        #[clap(
+            long = "skip-build",
+            help = "Don't run the build command from fission.yaml"
+        )]
+        skip_build: bool,
+        #[clap(
+            long = "skip-check",
+            help = "Publish even if the build directory has broken links"
+        )]
+        skip_check: bool,
+        #[clap(
+            long,
+            help = "Publish to an environment from the app config",
+            value_name = "NAME",
+            conflicts_with = "preview"
+        )]
+        env: Option<String>,
+        #[clap(
+            long,
+            help = "Publish to the preview app of a branch, creating it if needed",
+            value_name = "BRANCH"
+        )]
+        preview: Option<String>,
         #[clap(
             long = "ipfs-bin",
             help = "Path to IPFS binary [default: `which ipfs`]",
@@ -69,26 +284,60 @@ pub enum AppCommands {
             default_value = "1800",
             value_name = "SECONDS"
         )]
-        ipfs_timeout: String,
+        ipfs_timeout: u64,
         #[clap(
             long = "update-data",
             help = "Upload the data",
-            default_value = "True",
-            value_name = "ARG"
+            action = ArgAction::Set,
+            value_parser = BoolishValueParser::new(),
+            default_value = "true",
+            value_name = "BOOL"
         )]
-        update_data: String,
+        update_data: bool,
         #[clap(
             long = "update-dns",
             help = "Update DNS",
-            default_value = "True",
-            value_name = "ARG"
+            action = ArgAction::Set,
+            value_parser = BoolishValueParser::new(),
+            default_value = "true",
+            value_name = "BOOL"
         )]
-        update_dns: String,
+        update_dns: bool,
         #[clap(from_global)]
         verbose: bool,
         #[clap(from_global)]
         remote: Option<String>,
     },
+    #[clap(about = "Download a published version of the app")]
+    Pull {
+        #[clap(help = "The directory to write the app to", value_name = "DEST")]
+        dest: String,
+        #[clap(long, help = "The CID to pull [default: the app's current CID]")]
+        cid: Option<String>,
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(
+            long,
+            help = "The gateway to fall back to if IPFS can't be used",
+            default_value = IPFS_GATEWAY_URL,
+            value_name = "URL"
+        )]
+        gateway: String,
+        #[clap(
+            long = "ipfs-bin",
+            help = "Path to IPFS binary [default: `which ipfs`]",
+            value_name = "BIN_PATH"
+        )]
+        ipfs_bin: Option<String>,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
     #[clap(about = "Initialize an existing app")]
     Register {
         #[clap(
@@ -102,51 +351,244 @@ pub enum AppCommands {
         #[clap(
             short,
             long = "build-dir",
-            help = "The file path of the assets or directory to sync",
+            help = "The file path of the assets or directory to sync [default: detected from the app]",
             value_name = "PATH"
         )]
         build_dir: Option<String>,
         #[clap(short, long = "name", help = "Optional app name")]
         name: Option<String>,
+        // Registering no longer talks to IPFS, these are only kept so existing scripts still work
+        #[clap(long = "ipfs-bin", value_name = "BIN_PATH", hide = true)]
+        ipfs_bin: Option<String>,
+        #[clap(long = "ipfs-timeout", value_name = "SECONDS", hide = true)]
+        ipfs_timeout: Option<String>,
+        #[clap(from_global)]
+        verbose: bool,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Move an app to a new subdomain")]
+    Rename {
+        #[clap(help = "The name or URL of the app", value_name = "OLD")]
+        old: String,
+        #[clap(help = "The new name", value_name = "NEW")]
+        new: String,
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of an app config to update",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Point the app back at an earlier deploy")]
+    Rollback {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(
+            long,
+            help = "The CID, or how many deploys to go back as listed by `fission app history` [default: the previous deploy]",
+            value_name = "CID|N"
+        )]
+        to: Option<String>,
+        #[clap(
+            long,
+            help = "Roll back the app of this environment",
+            value_name = "NAME",
+            conflicts_with = "preview"
+        )]
+        env: Option<String>,
+        #[clap(
+            long,
+            help = "Roll back the preview app of this branch",
+            value_name = "BRANCH"
+        )]
+        preview: Option<String>,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Serve the app locally the way the gateway would")]
+    Serve {
+        #[clap(help = "The directory of the app", default_value = "./")]
+        path: String,
+        #[clap(
+            long,
+            help = "Serve this CID through IPFS instead of the build directory"
+        )]
+        cid: Option<String>,
+        #[clap(short, long, help = "The port to listen on", default_value_t = SERVE_PORT)]
+        port: u16,
+        #[clap(long, help = "Serve /index.html for paths that match nothing else")]
+        spa: bool,
         #[clap(
             long = "ipfs-bin",
             help = "Path to IPFS binary [default: `which ipfs`]",
             value_name = "BIN_PATH"
         )]
         ipfs_bin: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PreviewCommands {
+    #[clap(about = "List the app's previews")]
+    List {
         #[clap(
-            long = "ipfs-timeout",
-            help = "IPFS timeout",
-            default_value = "1800",
-            value_name = "SECONDS"
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
         )]
-        ipfs_timeout: String,
+        app_dir: String,
         #[clap(from_global)]
-        verbose: bool,
+        remote: Option<String>,
+    },
+    #[clap(about = "Destroy the previews that weren't published for a while")]
+    Prune {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(long, help = "Destroy every preview, expired or not")]
+        all: bool,
+        #[clap(long = "dry-run", help = "Show what would be destroyed")]
+        dry_run: bool,
         #[clap(from_global)]
         remote: Option<String>,
     },
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
-pub enum Potency {
-    Append,
-    Destroy,
-    SuperUser,
+#[derive(Subcommand)]
+pub enum DomainCommands {
+    #[clap(about = "Print the DNS records that serve the app at a custom domain")]
+    Add {
+        #[clap(help = "The domain, such as www.example.com")]
+        domain: String,
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+    },
+    #[clap(about = "List the app's custom domains")]
+    List {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(long, help = "Print the domains as JSON")]
+        json: bool,
+        #[clap(from_global)]
+        remote: Option<String>,
+    },
+    #[clap(about = "Print the DNS records to delete for a custom domain")]
+    Remove {
+        #[clap(help = "The domain, such as www.example.com")]
+        domain: String,
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+    },
+    #[clap(about = "Check the DNS records of a custom domain")]
+    Verify {
+        #[clap(help = "The domain, such as www.example.com")]
+        domain: String,
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(
+            long,
+            help = "The DNS over HTTPS resolver to check the records with",
+            default_value = DNS_RESOLVER_URL,
+            value_name = "URL"
+        )]
+        resolver: String,
+    },
 }
 
-impl Potency {
-    fn to_string(&self) -> String {
-        match self {
-            Potency::Append => "Append".to_string(),
-            Potency::Destroy => "Destroy".to_string(),
-            Potency::SuperUser => "Super_User".to_string(),
-        }
-    }
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    #[clap(about = "Check the app config for mistakes")]
+    Check {
+        #[clap(
+            short,
+            long = "app-dir",
+            help = "The directory of the app",
+            default_value = ".",
+            value_name = "PATH"
+        )]
+        app_dir: String,
+        #[clap(long, help = "Rewrite an outdated config in the current format")]
+        migrate: bool,
+    },
 }
 
-pub fn run_command(a: App) -> Result<()> {
-    match a.command {
+pub fn run_command(a: App) -> Result<Outcome> {
+    let result = match a.command {
+        AppCommands::Check {
+            path,
+            include_hidden,
+            env,
+        } => {
+            let app_dir = Path::new(&path);
+            let (root, filter, routing) = match AppConfig::find(app_dir)? {
+                Some(_) => {
+                    let mut config = AppConfig::load(app_dir)?;
+                    if let Some(env) = &env {
+                        config = config.for_env(env)?;
+                    }
+                    let root = build_dir(app_dir, &config)?;
+                    let routing = Routing::load(&config.routing, &root)?;
+                    (root, file_filter(&config, include_hidden), routing)
+                }
+                None => {
+                    let routing = Routing::load(&RoutingConfig::default(), app_dir)?;
+                    let filter = FileFilter::new(vec![], include_hidden);
+                    (app_dir.to_path_buf(), filter, routing)
+                }
+            };
+            println!("🔍 Checking {}", root.display());
+            let broken = check_links(&root, &filter, &routing)?;
+            check::print(&broken);
+            if !broken.is_empty() {
+                bail!("Found {} broken links", broken.len());
+            }
+            Ok(())
+        }
+        AppCommands::Config {
+            command: ConfigCommands::Check { app_dir, migrate },
+        } => config::check(Path::new(&app_dir), migrate),
         AppCommands::Delegate {
             app_name,
             did,
@@ -177,23 +619,108 @@ pub fn run_command(a: App) -> Result<()> {
 
             Ok(())
         }
-        AppCommands::Info { verbose, remote } => {
-            let remote = prepare_args(&HashMap::from([("-R", remote.as_ref())]));
-            let flags = prepare_flags(&HashMap::from([("-v", verbose)]));
-
-            Command::new("fission")
-                .args(["app", "info"])
-                .args(remote)
-                .args(flags)
-                .spawn()?
-                .wait()?;
+        AppCommands::Destroy { name, yes, remote } => {
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let url = resolve_url(&server, &name)?;
+            if !yes {
+                confirm_destroy(std::io::stdin().lock(), &url)?;
+            }
+            destroy(&server, &url)?;
+            println!("{} {}", "✅ Destroyed".bright_green(), url);
+            Ok(())
+        }
+        AppCommands::Diff {
+            path,
+            include_hidden,
+            env,
+            ipfs_bin,
+            remote,
+        } => {
+            let mut config = AppConfig::load(Path::new(&path))?;
+            if let Some(env) = &env {
+                config = config.for_env(env)?;
+            }
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let daemon = IpfsDaemon::load()?.with_ipfs_bin(ipfs_bin.map(PathBuf::from));
+            let (diff, changes) = daemon.run(|ipfs| {
+                let diff =
+                    AppDiff::compute(ipfs, &server, Path::new(&path), &config, include_hidden)?;
+                let changes = diff.changes(ipfs)?;
+                Ok((diff, changes))
+            })?;
 
+            if changes.is_empty() {
+                println!(
+                    "{} {} matches {}",
+                    "✅".bright_green(),
+                    diff.url.bright_blue(),
+                    diff.cid
+                );
+                return Ok(Outcome::Done);
+            }
+            match &diff.current_cid {
+                Some(current) => println!(
+                    "📋 {} ({}) differs from the build directory ({})",
+                    diff.url.bright_blue(),
+                    current,
+                    diff.cid
+                ),
+                None => println!("📋 {} hasn't been published yet", diff.url.bright_blue()),
+            }
+            for change in &changes {
+                change.print();
+            }
+            println!("  {} files changed", changes.len());
+            return Ok(Outcome::Differs);
+        }
+        AppCommands::Domain { command } => run_domain_command(command),
+        AppCommands::History {
+            app_dir,
+            env,
+            preview,
+        } => {
+            let app_dir = Path::new(&app_dir);
+            let config = config_for(app_dir, env.as_deref(), preview.as_deref())?;
+            history::print(&history::load_for(app_dir, &config.url)?);
+            Ok(())
+        }
+        AppCommands::Info {
+            app_dir,
+            json,
+            remote,
+        } => {
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let summary = info(&server, Path::new(&app_dir))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                summary.print();
+            }
+            Ok(())
+        }
+        AppCommands::List { json, remote } => {
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let apps = list(&server)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&apps)?);
+            } else {
+                print_list(&apps);
+            }
             Ok(())
         }
+        AppCommands::Previews { command } => run_preview_command(command),
         AppCommands::Publish {
             path,
             open,
             watch,
+            dry_run,
+            include_hidden,
+            allow_sensitive,
+            force,
+            skip_build,
+            skip_check,
+            env,
+            preview,
             ipfs_bin,
             ipfs_timeout,
             update_data,
@@ -201,30 +728,92 @@ pub fn run_command(a: App) -> Result<()> {
             verbose,
             remote,
         } => {
-            let args = prepare_args(&HashMap::from([
-                ("--ipfs-bin", ipfs_bin.as_ref()),
-                ("--ipfs-timeout", Some(ipfs_timeout).as_ref()),
-                ("--update-data", Some(update_data).as_ref()),
-                ("--update-dns", Some(update_dns).as_ref()),
-            ]));
-            let remote = prepare_args(&HashMap::from([("-R", remote.as_ref())]));
-            let flags = prepare_flags(&HashMap::from([
-                ("-o", open),
-                ("-w", watch),
-                ("-v", verbose),
-            ]));
+            let settings = Settings::load()?;
+            let server = ServerClient::new(&settings, remote.as_deref())?;
+            let daemon = IpfsDaemon::load()?
+                .with_ipfs_bin(ipfs_bin.map(PathBuf::from))
+                .with_timeout(Duration::from_secs(ipfs_timeout));
+            let options = PublishOptions {
+                update_data,
+                update_dns,
+                peers: cluster_peers(&settings),
+                persist_peering: !daemon.conn_info().is_remote(),
+                include_hidden,
+                allow_sensitive,
+                force,
+                skip_build,
+                skip_check,
+                env,
+                preview,
+                verbose,
+            };
 
-            Command::new("fission")
-                .args(["app", "publish"])
-                .arg(path)
-                .args(args)
-                .args(remote)
-                .args(flags)
-                .spawn()?
-                .wait()?;
+            if dry_run {
+                // A dry run leaves no trace, so it only uses a node that is already running
+                let ipfs = if daemon.is_running() {
+                    Some(&daemon)
+                } else {
+                    None
+                };
+                let plan = plan(ipfs, &server, Path::new(&path), &options)?;
+                plan.print();
+                return Ok(Outcome::Done);
+            }
+            if watch {
+                return daemon
+                    .run(|ipfs| {
+                        watch_app(ipfs, &server, Path::new(&path), &options, || {
+                            ipfs.is_stopping()
+                        })
+                    })
+                    .map(|()| Outcome::Done);
+            }
+
+            let published =
+                daemon.run(|ipfs| publish(ipfs, &server, Path::new(&path), &options))?;
+
+            println!(
+                "{} {}",
+                "✅ Published".bright_green(),
+                published.url.bright_blue()
+            );
+            if open {
+                open_in_browser(&published.url)?;
+            }
 
             Ok(())
         }
+        AppCommands::Pull {
+            dest,
+            cid,
+            app_dir,
+            gateway,
+            ipfs_bin,
+            remote,
+        } => {
+            let cid = match cid {
+                Some(cid) => cid,
+                None => {
+                    let config = AppConfig::load(Path::new(&app_dir))?;
+                    let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+                    match block_on(server.app_cid(&config.url))? {
+                        Some(cid) => cid,
+                        None => bail!("{} hasn't been published yet", config.url),
+                    }
+                }
+            };
+            let daemon = IpfsDaemon::load()?.with_ipfs_bin(ipfs_bin.map(PathBuf::from));
+            let stats = pull_with_fallback(&daemon, &gateway, &cid.parse()?, Path::new(&dest))?;
+            println!(
+                "{} {} files ({}) from {} to {}",
+                "✅ Pulled".bright_green(),
+                stats.files,
+                format_size(stats.bytes),
+                cid.bright_blue(),
+                dest
+            );
+            Ok(())
+        }
         AppCommands::Register {
             app_dir,
             build_dir,
@@ -234,24 +823,232 @@ pub fn run_command(a: App) -> Result<()> {
             verbose,
             remote,
         } => {
-            let args = prepare_args(&HashMap::from([
-                ("-a", Some(app_dir).as_ref()),
-                ("-b", build_dir.as_ref()),
-                ("-n", name.as_ref()),
-                ("--ipfs-bin", ipfs_bin.as_ref()),
-                ("--ipfs-timeout", Some(ipfs_timeout).as_ref()),
-            ]));
-            let remote = prepare_args(&HashMap::from([("-R", remote.as_ref())]));
-            let flags = prepare_flags(&HashMap::from([("-v", verbose)]));
+            if ipfs_bin.is_some() || ipfs_timeout.is_some() {
+                eprintln!(
+                    "{} --ipfs-bin and --ipfs-timeout are deprecated and ignored, as registering \
+                     an app no longer uses IPFS",
+                    "⚠️ ".yellow()
+                );
+            }
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let options = RegisterOptions {
+                build_dir: build_dir.map(PathBuf::from),
+                name,
+            };
+            let config = register(&server, Path::new(&app_dir), &options)?;
+            println!(
+                "{} {}",
+                "✅ Registered".bright_green(),
+                config.url.bright_blue()
+            );
+            if verbose {
+                println!("  Build directory: {}", config.build.dir.display());
+                if let Some(command) = &config.build.command {
+                    println!("  Build command: {}", command);
+                }
+            }
+            println!(
+                "Run `fission app publish` to publish {}",
+                config.build.dir.display()
+            );
+            Ok(())
+        }
+        AppCommands::Rename {
+            old,
+            new,
+            app_dir,
+            remote,
+        } => {
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let url = rename(&server, Path::new(&app_dir), &old, &new)?;
+            println!("{} {}", "✅ Renamed to".bright_green(), url.bright_blue());
+            Ok(())
+        }
+        AppCommands::Rollback {
+            app_dir,
+            to,
+            env,
+            preview,
+            remote,
+        } => {
+            let app_dir = Path::new(&app_dir);
+            let config = config_for(app_dir, env.as_deref(), preview.as_deref())?;
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let deploy = rollback(&server, app_dir, &config.url, to.as_deref())?;
+            println!(
+                "{} https://{} to {}",
+                "✅ Rolled back".bright_green(),
+                deploy.url,
+                deploy.cid.bright_blue()
+            );
+            Ok(())
+        }
+        AppCommands::Serve {
+            path,
+            cid,
+            port,
+            spa,
+            ipfs_bin,
+        } => {
+            if let Some(cid) = cid {
+                let cid: Cid = cid.parse()?;
+                let options = ServeOptions {
+                    port,
+                    host: cid_host(&cid),
+                    routing: RoutingConfig {
+                        spa,
+                        ..RoutingConfig::default()
+                    },
+                };
+                let daemon = IpfsDaemon::load()?.with_ipfs_bin(ipfs_bin.map(PathBuf::from));
+                return daemon
+                    .run(|ipfs| {
+                        serve(&IpfsSite::new(ipfs, cid.clone()), &options, || {
+                            ipfs.is_stopping()
+                        })
+                    })
+                    .map(|()| Outcome::Done);
+            }
+            let app_dir = Path::new(&path);
+            let (root, name, mut routing) = match AppConfig::find(app_dir)? {
+                Some(_) => {
+                    let config = AppConfig::load(app_dir)?;
+                    let name = config.url.split('.').next().unwrap_or("app").to_string();
+                    (build_dir(app_dir, &config)?, name, config.routing)
+                }
+                None => (
+                    app_dir.to_path_buf(),
+                    "app".to_string(),
+                    RoutingConfig::default(),
+                ),
+            };
+            routing.spa |= spa;
+            let options = ServeOptions {
+                port,
+                host: format!("{}.localhost", name),
+                routing,
+            };
+            println!("📂 Serving {}", root.display());
+            serve(&DirSite { root }, &options, || false)
+        }
+    };
+    result.map(|()| Outcome::Done)
+}
 
-            Command::new("fission")
-                .args(["app", "register"])
-                .args(args)
-                .args(remote)
-                .args(flags)
-                .spawn()?
-                .wait()?;
+fn run_domain_command(command: DomainCommands) -> Result<()> {
+    match command {
+        DomainCommands::Add { domain, app_dir } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            let records = domain::add(&config.url, &domain)?;
+            println!(
+                "Create these DNS records, then run `fission app domain verify {}`:",
+                domain
+            );
+            for record in records {
+                println!("  {}", record);
+            }
+            if domain::is_apex(&domain) {
+                println!(
+                    "{} is an apex domain, which can't have a CNAME. If your DNS provider has no \
+                     ALIAS, ANAME or CNAME flattening, add www.{} instead and redirect {} to it \
+                     at your provider.",
+                    domain, domain, domain
+                );
+            }
+            println!(
+                "The Fission server can't attach custom domains by itself yet. Once the records \
+                 check out, ask Fission support to serve {} at {}.",
+                config.url, domain
+            );
+            Ok(())
+        }
+        DomainCommands::List {
+            app_dir,
+            json,
+            remote,
+        } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let domains = match block_on(server.app_info(&config.url))? {
+                Some(app) => app.domains,
+                None => bail!("The server doesn't know {}", config.url),
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&domains)?);
+                return Ok(());
+            }
+            if domains.is_empty() {
+                println!("{} has no custom domains", config.url);
+            }
+            for domain in &domains {
+                println!("{}", domain.bright_blue());
+            }
+            Ok(())
+        }
+        DomainCommands::Remove { domain, app_dir } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            domain::check_domain(&domain)?;
+            println!(
+                "Delete these DNS records to stop {} from pointing at the app:",
+                domain
+            );
+            for record in domain::records(&config.url, &domain) {
+                println!("  {}", record);
+            }
+            println!(
+                "The Fission server can't detach custom domains by itself yet, so ask Fission \
+                 support to stop serving {} at {}.",
+                config.url, domain
+            );
+            Ok(())
+        }
+        DomainCommands::Verify {
+            domain,
+            app_dir,
+            resolver,
+        } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            let resolver = DohResolver::new(&resolver)?;
+            domain::verify(&resolver, &config.url, &domain)?;
+            println!(
+                "{} {} points at {}",
+                "✅".bright_green(),
+                domain.bright_blue(),
+                config.url
+            );
+            Ok(())
+        }
+    }
+}
 
+fn run_preview_command(command: PreviewCommands) -> Result<()> {
+    match command {
+        PreviewCommands::List { app_dir, remote } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            print_previews(&previews(&server, &config)?);
+            Ok(())
+        }
+        PreviewCommands::Prune {
+            app_dir,
+            all,
+            dry_run,
+            remote,
+        } => {
+            let config = AppConfig::load(Path::new(&app_dir))?;
+            let server = ServerClient::new(&Settings::load()?, remote.as_deref())?;
+            let pruned = prune(&server, &config, all, dry_run)?;
+            if pruned.is_empty() {
+                println!("No previews to prune");
+            }
+            let verb = if dry_run {
+                "Would destroy"
+            } else {
+                "Destroyed"
+            };
+            for url in &pruned {
+                println!("🗑  {} {}", verb, url.bright_blue());
+            }
             Ok(())
         }
     }
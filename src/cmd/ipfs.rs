@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use futures::executor::block_on;
+use serde_json::Value;
+
+use crate::ipfs::binary::{check_version, find_kubo};
+use crate::ipfs::config::{diff, merge, ConfigChange, KuboConfig};
+use crate::ipfs::daemon::IpfsDaemon;
+use crate::ipfs::peering::{cluster_peers, connect_to_cluster};
+use crate::ipfs::Ipfs as IpfsApi;
+use crate::settings::Settings;
+
+#[derive(Args)]
+pub struct Ipfs {
+    #[clap(subcommand)]
+    command: IpfsCommands,
+}
+
+#[derive(Subcommand)]
+pub enum IpfsCommands {
+    #[clap(about = "Inspect and change the IPFS config")]
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+    #[clap(about = "Check the IPFS node and its connection to the Fission cluster")]
+    Status,
+    #[clap(about = "List and manage the peers IPFS is connected to")]
+    Peers {
+        #[clap(subcommand)]
+        command: Option<PeersCommands>,
+    },
+    #[clap(about = "Show the peer ID, addresses and agent version of the IPFS node")]
+    Id,
+}
+
+#[derive(Subcommand)]
+pub enum PeersCommands {
+    #[clap(about = "Connect to a peer")]
+    Connect {
+        #[clap(help = "The multiaddr of the peer, ending in /p2p/<peer ID>")]
+        addr: String,
+    },
+    #[clap(about = "Disconnect from a peer")]
+    Disconnect {
+        #[clap(help = "The multiaddr of the peer, ending in /p2p/<peer ID>")]
+        addr: String,
+    },
+    #[clap(about = "Measure the round trip time to a peer")]
+    Ping {
+        #[clap(help = "The peer ID to ping")]
+        peer_id: String,
+        #[clap(short, long, default_value_t = 3, help = "The number of pings to send")]
+        count: u16,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    #[clap(about = "Print the current IPFS config")]
+    Show,
+    #[clap(about = "Show what applying a config file would change")]
+    Diff {
+        #[clap(help = "A JSON or YAML file with the config values to set")]
+        file: String,
+    },
+    #[clap(about = "Apply a config file to the IPFS config")]
+    Apply {
+        #[clap(help = "A JSON or YAML file with the config values to set")]
+        file: String,
+    },
+}
+
+pub fn run_command(i: Ipfs) -> Result<()> {
+    match i.command {
+        IpfsCommands::Config { command } => IpfsDaemon::load()?.run(|ipfs| match command {
+            ConfigCommands::Show => {
+                let config = block_on(ipfs.get_full_config())?;
+                println!("{}", serde_json::to_string_pretty(&config)?);
+                Ok(())
+            }
+            ConfigCommands::Diff { file } => {
+                let (_, changes) = plan_config(ipfs, &file)?;
+                print_changes(&changes);
+                Ok(())
+            }
+            ConfigCommands::Apply { file } => {
+                let (config, changes) = plan_config(ipfs, &file)?;
+                print_changes(&changes);
+                if !changes.is_empty() {
+                    block_on(ipfs.replace_config(&config))?;
+                    println!(
+                        "{}",
+                        "✅ Applied the IPFS config. Restart IPFS for every change to take effect."
+                            .bright_green()
+                    );
+                }
+                Ok(())
+            }
+        }),
+        IpfsCommands::Status => IpfsDaemon::load()?.run(|ipfs| {
+            let conn_info = ipfs.conn_info();
+            println!(
+                "🛰️  IPFS API: {} ({})",
+                conn_info.endpoint.to_string().bright_blue(),
+                if conn_info.is_remote() {
+                    "remote"
+                } else {
+                    "local"
+                }
+            );
+
+            let settings = Settings::load()?;
+            if !conn_info.is_remote() {
+                let bin = find_kubo(None, &settings)?;
+                println!(
+                    "📦 Kubo: {} ({})",
+                    check_version(&bin)?.to_string().bright_blue(),
+                    bin.display()
+                );
+            }
+
+            let peers = cluster_peers(&settings);
+            println!("🌐 Fission cluster peers:");
+            let health = block_on(connect_to_cluster(ipfs, &peers));
+            for peer in &health {
+                match &peer.result {
+                    Ok(latency) => println!("  ✅ {} ({:?})", peer.addr, latency),
+                    Err(e) => println!("  ❌ {}\n     {}", peer.addr, e.red()),
+                }
+            }
+            if !health.is_empty() && health.iter().all(|peer| peer.result.is_err()) {
+                bail!("Failed to connect to any of the Fission IPFS cluster peers");
+            }
+            Ok(())
+        }),
+        IpfsCommands::Peers { command } => IpfsDaemon::load()?.run(|ipfs| match command {
+            None => {
+                let mut peers = block_on(ipfs.get_connected())?;
+                peers.sort_by_key(|peer| peer.latency.unwrap_or(Duration::MAX));
+                for peer in &peers {
+                    let latency = match peer.latency {
+                        Some(latency) => format!("{:.1?}", latency),
+                        None => "-".to_string(),
+                    };
+                    println!(
+                        "{}  {:>10}  {:<8}  {}",
+                        peer.peer_id.bright_blue(),
+                        latency,
+                        peer.direction,
+                        peer.addr
+                    );
+                }
+                println!("{} peers connected", peers.len());
+                Ok(())
+            }
+            Some(PeersCommands::Connect { addr }) => block_on(ipfs.connect_to(&addr)),
+            Some(PeersCommands::Disconnect { addr }) => block_on(ipfs.disconnect(&addr)),
+            Some(PeersCommands::Ping { peer_id, count }) => {
+                let times = block_on(ipfs.ping(&peer_id, count))?;
+                for time in &times {
+                    println!("Pong from {}: {:.1?}", peer_id.bright_blue(), time);
+                }
+                if !times.is_empty() {
+                    let average = times.iter().sum::<Duration>() / times.len() as u32;
+                    println!("Average latency: {:.1?}", average);
+                }
+                Ok(())
+            }
+        }),
+        IpfsCommands::Id => IpfsDaemon::load()?.run(|ipfs| {
+            let id = block_on(ipfs.id())?;
+            println!("🆔 Peer ID: {}", id.peer_id.bright_blue());
+            println!("🤖 Agent version: {}", id.agent_version);
+            println!("📍 Addresses:");
+            for addr in &id.addresses {
+                println!("  {}", addr);
+            }
+            Ok(())
+        }),
+    }
+}
+
+/// Merges the declarative config in `file` over the current config, returning the resulting
+/// config along with what would change.
+fn plan_config(ipfs: &IpfsDaemon, file: &str) -> Result<(KuboConfig, Vec<ConfigChange>)> {
+    let contents =
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+    // YAML is a superset of JSON, so this handles both
+    let overlay: Value =
+        serde_yaml::from_str(&contents).with_context(|| format!("Failed to parse {}", file))?;
+
+    let current = serde_json::to_value(block_on(ipfs.get_full_config())?)?;
+    let mut merged = current.clone();
+    merge(&mut merged, &overlay);
+    let desired: KuboConfig = serde_json::from_value(merged)
+        .with_context(|| format!("{} is not a valid IPFS config", file))?;
+
+    let changes = diff(&current, &serde_json::to_value(&desired)?);
+    Ok((desired, changes))
+}
+
+fn print_changes(changes: &[ConfigChange]) {
+    if changes.is_empty() {
+        println!("{}", "The IPFS config is already up to date.".green());
+        return;
+    }
+    let show = |val: &Option<Value>| match val {
+        Some(val) => val.to_string(),
+        None => "(unset)".to_string(),
+    };
+    for change in changes {
+        println!(
+            "{}: {} -> {}",
+            change.path.bold(),
+            show(&change.old).red(),
+            show(&change.new).green()
+        );
+    }
+}
@@ -1,42 +1,100 @@
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, time::Duration};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::Value;
 
+use crate::ipfs::config::KuboConfig;
+use crate::ipfs::swarm::{NodeId, PeerInfo};
+use crate::ipfs::unixfs::Link;
+use crate::utils::file_filter::FileFilter;
+
+pub mod binary;
+pub mod cid;
+pub mod client;
+pub mod config;
 pub mod daemon;
+pub mod gateway;
+pub mod peering;
+pub mod swarm;
+pub mod unixfs;
 #[async_trait]
 pub trait Ipfs {
     /// This method uploads a file or directory at a given path to the IPFS swarm you are
-    /// currently connected to.
-    async fn add(&self, path:&Path) -> Result<HashMap<String, String>>;
+    /// currently connected to, skipping anything the filter leaves out.
+    async fn add(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>>;
+    /// This method computes the CIDs `add` would return for a path, without storing or
+    /// announcing anything.
+    async fn hash(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>>;
+    /// This method lists the entries of the directory with the given CID, fetching it from the
+    /// swarm if it isn't stored locally
+    async fn ls(&self, cid: &str) -> Result<Vec<Link>>;
+    /// This method returns the raw bytes of a single block, fetching it from the swarm if it
+    /// isn't stored locally
+    async fn get_block(&self, cid: &str) -> Result<Vec<u8>>;
     /// This method connects to the given address, adding the address to the current swarm
-    async fn connect_to(&self, peer_id:&str) -> Result<()>;
-    /// This method returns a list of all the addresses that are currently connected
-    async fn get_connected(&self) -> Result<Vec<String>>;
-    /// This method changes the value of a given property in the IPFS config
-    /// 
+    async fn connect_to(&self, peer_id: &str) -> Result<()>;
+    /// This method closes the connection to the given address
+    async fn disconnect(&self, peer_id: &str) -> Result<()>;
+    /// This method returns the peers that are currently connected, along with their address,
+    /// latency and which side opened the connection
+    async fn get_connected(&self) -> Result<Vec<PeerInfo>>;
+    /// This method pings a peer `count` times, returning the round trip time of each ping
+    ///
+    /// ```no_run
+    /// use fission::ipfs::daemon::IpfsDaemon;
+    /// use fission::ipfs::Ipfs;
+    /// use futures::executor::block_on;
+    ///
+    /// let ipfs = IpfsDaemon::default();
+    /// let times = block_on(ipfs.ping("12D3KooWQ2hL9NschcJ1Suqa1TybJc2ZaacqoQMBT3ziFC7Ye2BZ", 3)).unwrap();
+    /// ```
+    async fn ping(&self, peer_id: &str, count: u16) -> Result<Vec<Duration>>;
+    /// This method returns the peer ID, addresses and agent version of our own node
+    async fn id(&self) -> Result<NodeId>;
+    /// This method changes the value of a given property in the IPFS config. Any JSON value,
+    /// including numbers and null, can be set.
+    ///
     /// ```no_run
     /// use fission::ipfs::daemon::IpfsDaemon;
     /// use fission::ipfs::Ipfs;
     /// use futures::executor::block_on;
     /// use serde_json::Value;
-    /// 
+    ///
     /// let ipfs = IpfsDaemon::default();
     /// let config_value = Value::from("11GB");
     /// block_on(ipfs.set_config("Datastore.StorageMax", &config_value)).unwrap();
     /// ```
-    async fn set_config(&self, property:&str, val:&Value) -> Result<()>;
-    
+    async fn set_config(&self, property: &str, val: &Value) -> Result<()>;
+
     /// This method returns the value a property in the IPFS config
-    /// 
+    ///
     /// ```no_run
     /// use fission::ipfs::daemon::IpfsDaemon;
     /// use fission::ipfs::Ipfs;
     /// use futures::executor::block_on;
-    /// 
+    ///
     /// let ipfs = IpfsDaemon::default();
     /// let config_value = block_on(ipfs.get_config("Datastore.StorageMax")).unwrap();
     /// ```
     async fn get_config(&self, property: &str) -> Result<Value>;
+
+    /// This method returns the whole IPFS config
+    ///
+    /// ```no_run
+    /// use fission::ipfs::daemon::IpfsDaemon;
+    /// use fission::ipfs::Ipfs;
+    /// use futures::executor::block_on;
+    ///
+    /// let ipfs = IpfsDaemon::default();
+    /// let mut config = block_on(ipfs.get_full_config()).unwrap();
+    /// let datastore = config.datastore.get_or_insert(None).get_or_insert_with(Default::default);
+    /// datastore.storage_max = Some(Some("11GB".to_string()));
+    /// block_on(ipfs.replace_config(&config)).unwrap();
+    /// ```
+    async fn get_full_config(&self) -> Result<KuboConfig>;
+
+    /// This method replaces the whole IPFS config with the one given. Most changes only take
+    /// effect after the daemon restarts.
+    async fn replace_config(&self, config: &KuboConfig) -> Result<()>;
 }
@@ -0,0 +1,99 @@
+use std::env::consts::EXE_SUFFIX;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use semver::{BuildMetadata, Prerelease, Version, VersionReq};
+
+use crate::settings::{config_dir, Settings};
+use crate::utils::config::{IPFS_BIN_ENV, IPFS_EXE, IPFS_SUPPORTED_VERSIONS};
+
+/// This function finds the Kubo binary to launch. In order, it checks the `--ipfs-bin` flag, the
+/// `ipfs.bin` setting, the `FISSION_IPFS_BIN` environment variable, the `PATH` and finally the
+/// directories Kubo is commonly installed to.
+pub fn find_kubo(flag: Option<&Path>, settings: &Settings) -> Result<PathBuf> {
+    let configured = flag
+        .map(Path::to_path_buf)
+        .or_else(|| settings.ipfs.bin.clone())
+        .or_else(|| std::env::var_os(IPFS_BIN_ENV).map(PathBuf::from));
+    if let Some(bin) = configured {
+        if !bin.is_file() {
+            bail!("The configured Kubo binary {:?} does not exist", bin);
+        }
+        return Ok(bin);
+    }
+
+    let exe = format!("{}{}", IPFS_EXE, EXE_SUFFIX);
+    let path_dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    path_dirs
+        .into_iter()
+        .chain(well_known_dirs())
+        .map(|dir| dir.join(&exe))
+        .find(|bin| bin.is_file())
+        .with_context(|| {
+            format!(
+                "Couldn't find the Kubo binary. Install it from https://docs.ipfs.tech/install/command-line/ \
+                 or point --ipfs-bin, the `ipfs.bin` setting or {} at it.",
+                IPFS_BIN_ENV
+            )
+        })
+}
+
+fn well_known_dirs() -> Vec<PathBuf> {
+    let mut candidates = vec![config_dir().join("bin")];
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".local/bin"));
+        candidates.push(home.join("go/bin"));
+    }
+    if cfg!(unix) {
+        for dir in [
+            "/usr/local/bin",
+            "/opt/homebrew/bin",
+            "/usr/bin",
+            "/snap/bin",
+        ] {
+            candidates.push(PathBuf::from(dir));
+        }
+    }
+    candidates
+}
+
+/// This function asks the binary for its version and fails with a clear error if it is outside
+/// the range of Kubo versions the CLI supports.
+pub fn check_version(bin: &Path) -> Result<Version> {
+    let output = Command::new(bin)
+        .args(["version", "--number"])
+        .output()
+        .with_context(|| format!("Failed to run {:?}", bin))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to get the version of {:?}: {}",
+            bin,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let version = parse_version(&String::from_utf8_lossy(&output.stdout))?;
+
+    let supported = VersionReq::parse(IPFS_SUPPORTED_VERSIONS)?;
+    if !supported.matches(&version) {
+        bail!(
+            "Kubo {} at {:?} is not supported, please install a version matching {}",
+            version,
+            bin,
+            IPFS_SUPPORTED_VERSIONS
+        );
+    }
+    Ok(version)
+}
+
+/// Parses the output of `ipfs version --number`. Development builds are treated as the release
+/// they lead up to, so `0.18.0-dev` counts as `0.18.0`.
+pub fn parse_version(output: &str) -> Result<Version> {
+    let mut version = Version::parse(output.trim())
+        .with_context(|| format!("Unrecognised Kubo version {:?}", output.trim()))?;
+    version.pre = Prerelease::EMPTY;
+    version.build = BuildMetadata::EMPTY;
+    Ok(version)
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+/// The multicodec of DAG-PB nodes, which UnixFS directories and large files are made of
+pub const DAG_PB: u64 = 0x70;
+/// The multicodec of raw leaves
+pub const RAW: u64 = 0x55;
+const SHA2_256: u64 = 0x12;
+const IDENTITY: u64 = 0x00;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// A content identifier, enough of one to fetch a block and check that it is what we asked for
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cid {
+    pub version: u64,
+    pub codec: u64,
+    pub hash_code: u64,
+    pub digest: Vec<u8>,
+}
+
+impl Cid {
+    /// This method computes the CIDv1 of a block using SHA-256
+    pub fn for_block(codec: u64, data: &[u8]) -> Self {
+        Self {
+            version: 1,
+            codec,
+            hash_code: SHA2_256,
+            digest: Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// This method parses a CID in its binary form, as DAG-PB links store them
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        // CIDv0 is a bare SHA-256 multihash
+        if bytes.len() == 34 && bytes[0] == SHA2_256 as u8 && bytes[1] == 32 {
+            return Ok(Self {
+                version: 0,
+                codec: DAG_PB,
+                hash_code: SHA2_256,
+                digest: bytes[2..].to_vec(),
+            });
+        }
+        let mut rest = bytes;
+        let version = read_varint(&mut rest)?;
+        if version != 1 {
+            bail!("Unsupported CID version {}", version);
+        }
+        let codec = read_varint(&mut rest)?;
+        let hash_code = read_varint(&mut rest)?;
+        let len = read_varint(&mut rest)? as usize;
+        if rest.len() != len {
+            bail!(
+                "Invalid CID: the digest is {} bytes, not {}",
+                rest.len(),
+                len
+            );
+        }
+        Ok(Self {
+            version,
+            codec,
+            hash_code,
+            digest: rest.to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        if self.version == 1 {
+            write_varint(&mut bytes, 1);
+            write_varint(&mut bytes, self.codec);
+        }
+        write_varint(&mut bytes, self.hash_code);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    /// This method checks that `data` hashes to this CID
+    pub fn verify(&self, data: &[u8]) -> Result<()> {
+        let matches = match self.hash_code {
+            SHA2_256 => Sha256::digest(data).as_slice() == self.digest.as_slice(),
+            IDENTITY => data == self.digest.as_slice(),
+            code => bail!(
+                "Cannot verify {}, hash function {:#x} is unsupported",
+                self,
+                code
+            ),
+        };
+        if !matches {
+            bail!("The block received for {} does not match its hash", self);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cid {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = if s.starts_with("Qm") {
+            bs58::decode(s).into_vec()?
+        } else if let Some(base32) = s.strip_prefix('b') {
+            base32_decode(base32).with_context(|| format!("Invalid CID {:?}", s))?
+        } else {
+            bail!(
+                "Unsupported CID {:?}, expected a CIDv0 or a base32 CIDv1",
+                s
+            )
+        };
+        Cid::from_bytes(&bytes).with_context(|| format!("Invalid CID {:?}", s))
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.version == 0 {
+            write!(f, "{}", bs58::encode(self.to_bytes()).into_string())
+        } else {
+            write!(f, "b{}", base32_encode(&self.to_bytes()))
+        }
+    }
+}
+
+/// Reads an unsigned LEB128 varint off the front of `bytes`
+pub fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Ok(value);
+        }
+    }
+    bail!("Invalid varint")
+}
+
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(s: &str) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = match BASE32_ALPHABET
+            .iter()
+            .position(|x| *x == c.to_ascii_lowercase())
+        {
+            Some(x) => x as u32,
+            None => bail!("Invalid base32 character {:?}", c as char),
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    Ok(out)
+}
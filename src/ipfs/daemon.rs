@@ -1,54 +1,216 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, SystemTime};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use colored::Colorize;
 use futures::executor::block_on;
 use graceful::SignalGuard;
-use ipfs_api_backend_hyper::{IpfsApi, IpfsClient, Logger, LoggingLevel, TryFromUri};
+use serde::Deserialize;
 use serde_json::Value;
 use tokio::runtime::Runtime;
 
+use crate::ipfs::binary::{check_version, find_kubo};
+use crate::ipfs::client::{IpfsAuth, IpfsEndpoint, KuboClient, MultipartForm};
+use crate::ipfs::config::KuboConfig;
+use crate::ipfs::swarm::{parse_go_duration, Direction, NodeId, PeerInfo};
+use crate::ipfs::unixfs::Link;
 use crate::ipfs::Ipfs;
+use crate::settings::Settings;
 use crate::utils::config::{
-    IPFS_ADDR, IPFS_API_PORT, IPFS_BOOT_TIME_OUT, IPFS_EXE, IPFS_SLEEP_LENGTH,
+    IPFS_ADDR, IPFS_API_ENV, IPFS_API_PORT, IPFS_BOOT_TIME_OUT, IPFS_CA_CERT_ENV,
+    IPFS_SLEEP_LENGTH, IPFS_TOKEN_ENV,
 };
+use crate::utils::file_filter::FileFilter;
 
 /// This struct is a wrapper for the information needed to point the IPFS daemon at a diffrent address
 /// than the default.
 #[derive(Clone, Debug)]
 pub struct IpfsConnInfo {
-    pub address: String,
-    pub port: u16,
+    pub endpoint: IpfsEndpoint,
+    pub auth: Option<IpfsAuth>,
+    /// A PEM encoded CA certificate to trust in addition to the system roots
+    pub ca_cert: Option<PathBuf>,
+    /// Whether the API was configured explicitly, in the settings or through `FISSION_IPFS_API`.
+    /// Such a daemon is run by someone else, so the CLI never launches or shuts it down.
+    pub remote: bool,
+}
+
+impl IpfsConnInfo {
+    /// This method builds the connection info from the user's settings, letting the
+    /// `FISSION_IPFS_API`, `FISSION_IPFS_TOKEN` and `FISSION_IPFS_CA_CERT` environment variables
+    /// override them. With nothing configured, this is the local daemon the CLI manages.
+    pub fn load() -> Result<Self> {
+        let settings = Settings::load()?.ipfs;
+        let mut conn_info = Self::default();
+
+        if let Some(api) = std::env::var(IPFS_API_ENV).ok().or(settings.api) {
+            let (endpoint, url_auth) = IpfsEndpoint::parse_with_auth(&api)?;
+            conn_info.endpoint = endpoint;
+            conn_info.auth = url_auth;
+            conn_info.remote = true;
+        }
+        if let Some(auth) = settings.auth {
+            conn_info.auth = Some(auth);
+        }
+        if let Ok(token) = std::env::var(IPFS_TOKEN_ENV) {
+            conn_info.auth = Some(IpfsAuth::Bearer(token));
+        }
+        conn_info.ca_cert = std::env::var_os(IPFS_CA_CERT_ENV)
+            .map(PathBuf::from)
+            .or(settings.ca_cert);
+
+        Ok(conn_info)
+    }
+
+    /// Whether this points anywhere other than a local daemon the CLI launches itself. Remote
+    /// daemons are never launched or shut down by the CLI.
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+}
+
+impl Default for IpfsConnInfo {
+    fn default() -> Self {
+        Self {
+            endpoint: IpfsEndpoint::Tcp {
+                https: false,
+                host: IPFS_ADDR.to_string(),
+                port: IPFS_API_PORT,
+                base_path: String::new(),
+            },
+            auth: None,
+            ca_cert: None,
+            remote: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AddResponse {
+    name: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LsLink {
+    name: String,
+    hash: String,
+    size: u64,
+    /// The UnixFS node type, 1 for directories
+    #[serde(rename = "Type")]
+    kind: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LsObject {
+    links: Vec<LsLink>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LsResponse {
+    objects: Vec<LsObject>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StringsResponse {
+    strings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SwarmPeer {
+    addr: String,
+    peer: String,
+    #[serde(default)]
+    latency: String,
+    #[serde(default)]
+    direction: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SwarmPeersResponse {
+    peers: Option<Vec<SwarmPeer>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PingResponse {
+    success: bool,
+    /// In nanoseconds
+    time: u64,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct IdResponse {
+    #[serde(rename = "ID")]
+    id: String,
+    addresses: Option<Vec<String>>,
+    agent_version: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConfigResponse {
+    value: Value,
 }
 
 pub struct IpfsDaemon {
     conn_info: IpfsConnInfo,
-    client: IpfsClient,
+    /// Overrides which Kubo binary gets launched, see `binary::find_kubo`
+    ipfs_bin: Option<PathBuf>,
+    client: KuboClient,
     tokio: Runtime,
+    /// Set once a shutdown signal was received, shared between clones
+    stopping: Arc<AtomicBool>,
 }
 
 impl IpfsDaemon {
     /// This method launches the IPFS daemon on the port/address given when the instance was
     /// created. During this proccess, it also creates a thread that listens for a shutdown signal
     /// and stops the IPFS daemon gracefully if the signal is given.
+    ///
+    /// If the instance points at a remote daemon, nothing is launched and this method only waits
+    /// for the remote API to respond. The signal is still listened for, see `is_stopping`.
     pub async fn launch(&self) -> Result<()> {
+        if self.conn_info.is_remote() {
+            println!("Using the IPFS API at {}...", self.client.endpoint());
+            self.await_ready().await?;
+            self.handle_signals();
+            return Ok(());
+        }
+
         //launch the daemon
-        let api_addr = format!(
-            "/ip4/{}/tcp/{}",
-            self.conn_info.address, self.conn_info.port
-        );
-        println!("Launching IPFS...");
-        Command::new(IPFS_EXE)
+        let api_addr = match self.client.endpoint().api_multiaddr() {
+            Some(x) => x,
+            None => bail!(
+                "{} {}",
+                "Cannot launch a local IPFS daemon for".red(),
+                self.client.endpoint()
+            ),
+        };
+        let bin = find_kubo(self.ipfs_bin.as_deref(), &Settings::load()?)?;
+        let version = check_version(&bin)?;
+        println!("Launching IPFS (Kubo {})...", version);
+        Command::new(&bin)
             .arg("--api")
             .arg(&api_addr)
             .arg("daemon")
             .spawn()
-            .unwrap_or_else(|e| panic!("Failed to start IPFS daemon: {}\n This error may be because the Kubo binary is not on your PATH.", e));
+            .with_context(|| format!("Failed to start the IPFS daemon with {:?}", bin))?;
 
         // Wait ipfs to be ready
         println!("Waiting for IPFS to ready..");
@@ -57,17 +219,31 @@ impl IpfsDaemon {
         // Reduce log level for IPFS
         self.tokio.block_on(async {
             self.client
-                .log_level(Logger::All, LoggingLevel::Error)
+                .request("log/level", &[("arg", "all"), ("arg", "error")])
                 .await
         })?;
 
         // Setup graceful shutdown
         println!("Creating graceful shutdown for IPFS...");
+        self.handle_signals();
+
+        println!("{}", "IPFS has launched successfully!!".green());
+        Ok(())
+    }
+
+    /// Waits for a shutdown signal on a separate thread, then marks the instance as stopping and
+    /// stops the daemon.
+    fn handle_signals(&self) {
         let me = self.clone();
         thread::spawn(move || {
             let signal_guard = SignalGuard::new();
 
             signal_guard.at_exit(move |sig| {
+                me.stopping.store(true, Ordering::SeqCst);
+                if me.conn_info.is_remote() {
+                    println!("Signal {} received. Stopping...", sig);
+                    return;
+                }
                 println!("Signal {} received. Attempting to stop IPFS...", sig);
                 match me.shutdown() {
                     Ok(_) => println!("{}", "IPFS has shutdown successfully.".green()),
@@ -75,27 +251,108 @@ impl IpfsDaemon {
                 };
             });
         });
+    }
 
-        println!("{}", "IPFS has launched successfully!!".green());
-        Ok(())
+    /// This method returns whether a shutdown signal was received since the daemon launched.
+    /// Long running work, such as watching an app, should check it and wind down when it is set.
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
     }
 
     /// This method sends an http signal to the IPFS deamon to shutdown.
     ///
-    /// Note 1: This method should work even if the daemon was not started by this proccess.
+    /// Note 1: This method should work even if the daemon was not started by this proccess, as
+    /// long as it is the local daemon. Remote daemons are left running.
     ///
     /// Note 2: This method returns when it recieves an http response from the daemon, not
     /// when the proccess has actually stopped.
     pub fn shutdown(&self) -> Result<()> {
+        if self.conn_info.is_remote() {
+            return Ok(());
+        }
         self.tokio
-            .block_on(async { block_on(self.client.shutdown()) })?;
+            .block_on(async { block_on(self.client.request("shutdown", &[])) })?;
         Ok(())
     }
 
+    /// This method returns whether the API already answers, without launching anything
+    pub fn is_running(&self) -> bool {
+        block_on(self.is_ipfs_ready())
+    }
+
+    pub fn conn_info(&self) -> &IpfsConnInfo {
+        &self.conn_info
+    }
+
+    /// This method sets the Kubo binary to launch, taking priority over the user's settings
+    pub fn with_ipfs_bin(mut self, ipfs_bin: Option<PathBuf>) -> Self {
+        self.ipfs_bin = ipfs_bin;
+        self
+    }
+
+    /// This method creates an instance for the daemon described by the user's settings
+    pub fn load() -> Result<Self> {
+        Self::try_from(IpfsConnInfo::load()?)
+    }
+
+    /// This method launches the daemon, runs `f` against it and then shuts the daemon down again,
+    /// whether or not `f` succeeded. If a signal already stopped the daemon, it is left alone.
+    pub fn run<T>(&self, f: impl FnOnce(&IpfsDaemon) -> Result<T>) -> Result<T> {
+        block_on(self.launch())?;
+        let res = f(self);
+        if !self.is_stopping() {
+            self.shutdown()?;
+        }
+        res
+    }
+
+    /// This method sets how long any single request to IPFS may take before it is abandoned
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client.set_timeout(Some(timeout));
+        self
+    }
+
+    /// Uploads `path` through the add RPC. Names are relative to the parent of `path`, so adding
+    /// `a/b` returns hashes for `b` and everything under it.
+    fn add_with_args(
+        &self,
+        path: &Path,
+        filter: &FileFilter,
+        args: &[(&str, &str)],
+    ) -> Result<HashMap<String, String>> {
+        let prefix = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut form = MultipartForm::default();
+        for entry in filter.walk(path)? {
+            let name = match entry.path().strip_prefix(prefix)?.to_str() {
+                Some(x) => x.to_string(),
+                None => bail!("failed to get path as string a file"),
+            };
+            if entry.file_type().is_dir() {
+                form.add_directory(&name);
+            } else if entry.file_type().is_file() {
+                form.add_file_at(&name, entry.path());
+            }
+        }
+
+        let content_type = form.content_type();
+        let response = self.tokio.block_on(async {
+            self.client
+                .request_with_body("add", args, Some(&content_type), form.into_body())
+                .await
+        })?;
+        // The add RPC streams one JSON object per line, one for each file and directory
+        let mut hashes = HashMap::new();
+        for line in response.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+            let res: AddResponse = serde_json::from_slice(line)?;
+            hashes.insert(res.name, res.hash);
+        }
+        Ok(hashes)
+    }
+
     async fn is_ipfs_ready(&self) -> bool {
         let res = self
             .tokio
-            .block_on(async { self.client.config_show().await });
+            .block_on(async { self.client.request("config/show", &[]).await });
         return match res {
             Ok(_) => true,
             Err(_) => false,
@@ -129,17 +386,22 @@ impl IpfsDaemon {
 impl TryFrom<IpfsConnInfo> for IpfsDaemon {
     type Error = anyhow::Error;
     /// This is one of the two ways to make a new instance of the IPFS daemon struct. Use this
-    /// method when you want to make a new instance and you need to set the port and address of
-    /// the daemon you are attempting to reference
+    /// method when you want to make a new instance and you need to set the endpoint and
+    /// credentials of the daemon you are attempting to reference
     fn try_from(conn_info: IpfsConnInfo) -> Result<Self> {
-        let client =
-            IpfsClient::from_host_and_port("http".parse()?, &conn_info.address, conn_info.port)?;
+        let client = KuboClient::new(
+            conn_info.endpoint.clone(),
+            conn_info.auth.clone(),
+            conn_info.ca_cert.as_deref(),
+        )?;
 
         let runtime = tokio::runtime::Runtime::new()?;
         Ok(Self {
             client,
             tokio: runtime,
             conn_info,
+            ipfs_bin: None,
+            stopping: Arc::new(AtomicBool::new(false)),
         })
     }
 }
@@ -152,11 +414,7 @@ impl Default for IpfsDaemon {
     /// Note: This method has the possiblity of throwing an error if the configuration of the port and
     /// address is not setup right.
     fn default() -> Self {
-        Self::try_from(IpfsConnInfo {
-            address: IPFS_ADDR.to_string(),
-            port: IPFS_API_PORT,
-        })
-        .unwrap()
+        Self::try_from(IpfsConnInfo::default()).unwrap()
     }
 }
 impl Clone for IpfsDaemon {
@@ -165,72 +423,174 @@ impl Clone for IpfsDaemon {
             client: self.client.clone(),
             tokio: Runtime::new().unwrap(),
             conn_info: self.conn_info.clone(),
+            ipfs_bin: self.ipfs_bin.clone(),
+            stopping: self.stopping.clone(),
         };
     }
 }
 
 #[async_trait]
 impl Ipfs for IpfsDaemon {
-    async fn add(&self, path: &Path) -> Result<HashMap<String, String>> {
-        let response_list = self
+    async fn add(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>> {
+        self.add_with_args(path, filter, &[])
+    }
+    async fn hash(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>> {
+        self.add_with_args(path, filter, &[("only-hash", "true")])
+    }
+    async fn get_block(&self, cid: &str) -> Result<Vec<u8>> {
+        let block = self
             .tokio
-            .block_on(async { self.client.add_path(path).await })?;
-        return Ok(response_list
+            .block_on(async { self.client.request("block/get", &[("arg", cid)]).await })?;
+        Ok(block.to_vec())
+    }
+    async fn ls(&self, cid: &str) -> Result<Vec<Link>> {
+        let res = self.tokio.block_on(async {
+            self.client
+                .request_json::<LsResponse>(
+                    "ls",
+                    &[("arg", cid), ("size", "true"), ("resolve-type", "true")],
+                )
+                .await
+        })?;
+        Ok(res
+            .objects
             .into_iter()
-            .map(|res| (res.name, res.hash))
-            .collect());
+            .flat_map(|object| object.links)
+            .map(|link| Link {
+                name: link.name,
+                cid: link.hash,
+                size: link.size,
+                is_dir: link.kind == 1,
+            })
+            .collect())
     }
     async fn connect_to(&self, peer_id: &str) -> Result<()> {
+        // Failures come back as an error response, the messages are only informational
         let messages = self
             .tokio
-            .block_on(async { self.client.swarm_connect(peer_id).await })?
+            .block_on(async {
+                self.client
+                    .request_json::<StringsResponse>("swarm/connect", &[("arg", peer_id)])
+                    .await
+            })?
             .strings;
         for msg in messages {
             println!("{}", msg.blue());
-            if !msg.contains("success") {
-                bail!(msg);
-            }
         }
         return Ok(());
     }
-    async fn get_connected(&self) -> Result<Vec<String>> {
-        let peers = self
+    async fn disconnect(&self, peer_id: &str) -> Result<()> {
+        let messages = self
             .tokio
-            .block_on(async { self.client.swarm_peers().await })?;
-        Ok(peers.peers.into_iter().map(|peer| peer.addr).collect())
+            .block_on(async {
+                self.client
+                    .request_json::<StringsResponse>("swarm/disconnect", &[("arg", peer_id)])
+                    .await
+            })?
+            .strings;
+        for msg in messages {
+            println!("{}", msg.blue());
+        }
+        Ok(())
     }
-    async fn get_config(&self, prop: &str) -> Result<Value> {
-        let config = self
+    async fn get_connected(&self) -> Result<Vec<PeerInfo>> {
+        let peers = self.tokio.block_on(async {
+            self.client
+                .request_json::<SwarmPeersResponse>(
+                    "swarm/peers",
+                    &[("latency", "true"), ("direction", "true")],
+                )
+                .await
+        })?;
+        peers
+            .peers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|peer| {
+                let latency = match peer.latency.as_str() {
+                    "" | "n/a" => None,
+                    latency => Some(parse_go_duration(latency)?),
+                };
+                Ok(PeerInfo {
+                    peer_id: peer.peer,
+                    addr: peer.addr,
+                    latency,
+                    direction: Direction::from(peer.direction),
+                })
+            })
+            .collect()
+    }
+    async fn ping(&self, peer_id: &str, count: u16) -> Result<Vec<Duration>> {
+        let count = count.to_string();
+        let response = self.tokio.block_on(async {
+            self.client
+                .request("ping", &[("arg", peer_id), ("count", &count)])
+                .await
+        })?;
+        // The ping RPC streams one JSON object per line. Lines with a time of zero are only
+        // status text, like the header and the closing average.
+        let mut times = vec![];
+        for line in response.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+            let res: PingResponse = serde_json::from_slice(line)?;
+            if !res.success {
+                bail!("Failed to ping {}: {}", peer_id, res.text);
+            }
+            if res.time > 0 {
+                times.push(Duration::from_nanos(res.time));
+            }
+        }
+        Ok(times)
+    }
+    async fn id(&self) -> Result<NodeId> {
+        let id = self
             .tokio
-            .block_on(async { self.client.config_get_json(prop).await })?;
+            .block_on(async { self.client.request_json::<IdResponse>("id", &[]).await })?;
+        Ok(NodeId {
+            peer_id: id.id,
+            addresses: id.addresses.unwrap_or_default(),
+            agent_version: id.agent_version,
+        })
+    }
+    async fn get_config(&self, prop: &str) -> Result<Value> {
+        let config = self.tokio.block_on(async {
+            self.client
+                .request_json::<ConfigResponse>("config", &[("arg", prop)])
+                .await
+        })?;
         return Ok(config.value);
     }
     async fn set_config(&self, prop: &str, val: &Value) -> Result<()> {
-        if val.is_boolean() {
+        if let Some(val) = val.as_str() {
             self.tokio.block_on(async {
                 self.client
-                    .config_set_bool(prop, val.as_bool().unwrap())
+                    .request("config", &[("arg", prop), ("arg", val)])
                     .await
             })?;
             return Ok(());
         }
-        if val.is_string() {
-            self.tokio.block_on(async {
-                self.client
-                    .config_set_string(prop, val.as_str().unwrap())
-                    .await
-            })?;
-            return Ok(());
-        }
-        if val.is_number() || val.is_null() {
-            bail!(
-                "{}",
-                "The IPFS config API does not suport null or number json types"
-            )
-        }
-        self.tokio
-            .block_on(async { self.client.config_set_json(prop, &val.to_string()).await })?;
+        // Everything else, including numbers and null, is sent as JSON
+        let val = val.to_string();
+        self.tokio.block_on(async {
+            self.client
+                .request("config", &[("arg", prop), ("arg", &val), ("json", "true")])
+                .await
+        })?;
 
         return Ok(());
     }
+    async fn get_full_config(&self) -> Result<KuboConfig> {
+        self.tokio
+            .block_on(async { self.client.request_json("config/show", &[]).await })
+    }
+    async fn replace_config(&self, config: &KuboConfig) -> Result<()> {
+        let mut form = MultipartForm::default();
+        form.add_file("config", &serde_json::to_vec(config)?);
+        let content_type = form.content_type();
+        self.tokio.block_on(async {
+            self.client
+                .request_with_body("config/replace", &[], Some(&content_type), form.into_body())
+                .await
+        })?;
+        Ok(())
+    }
 }
@@ -0,0 +1,53 @@
+use anyhow::{bail, Context, Result};
+use hyper::client::HttpConnector;
+use hyper::header::ACCEPT;
+use hyper::{Body, Client, Request};
+use hyper_tls::HttpsConnector;
+use tokio::runtime::Runtime;
+
+use crate::ipfs::cid::Cid;
+use crate::ipfs::unixfs::BlockSource;
+
+/// A client for an HTTP gateway that serves raw blocks, as described by the trustless gateway
+/// spec. Nothing it returns can be trusted until it was checked against its CID.
+pub struct Gateway {
+    base_url: String,
+    client: Client<HttpsConnector<HttpConnector>>,
+    tokio: Runtime,
+}
+
+impl Gateway {
+    pub fn new(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: Client::builder().build(HttpsConnector::new()),
+            tokio: Runtime::new()?,
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+impl BlockSource for Gateway {
+    fn fetch_block(&self, cid: &Cid) -> Result<Vec<u8>> {
+        let uri = format!("{}/ipfs/{}?format=raw", self.base_url, cid);
+        self.tokio.block_on(async {
+            let req = Request::get(&uri)
+                .header(ACCEPT, "application/vnd.ipld.raw")
+                .body(Body::empty())?;
+            let res = self
+                .client
+                .request(req)
+                .await
+                .with_context(|| format!("Failed to reach the gateway at {}", self.base_url))?;
+            let status = res.status();
+            let bytes = hyper::body::to_bytes(res.into_body()).await?;
+            if !status.is_success() {
+                bail!("The gateway failed to return {} ({})", cid, status);
+            }
+            Ok(bytes.to_vec())
+        })
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use crate::ipfs::config::PeeringPeer;
+use crate::ipfs::Ipfs;
+use crate::settings::Settings;
+use crate::utils::config::{FISSION_CLUSTER_PEERS, IPFS_PEERS_ENV};
+
+/// The outcome of dialing a single cluster peer
+pub struct PeerHealth {
+    pub addr: String,
+    /// How long the connection took to establish, or why it failed
+    pub result: std::result::Result<Duration, String>,
+}
+
+/// This function returns the cluster peers to keep connected to. `FISSION_IPFS_PEERS` (comma
+/// separated) wins over the `peers` setting, which wins over Fission's production cluster. Point
+/// either of the first two at a local cluster to test against it.
+pub fn cluster_peers(settings: &Settings) -> Vec<String> {
+    if let Ok(peers) = std::env::var(IPFS_PEERS_ENV) {
+        return peers
+            .split(',')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    if !settings.peers.is_empty() {
+        return settings.peers.clone();
+    }
+    FISSION_CLUSTER_PEERS
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// This function dials every peer and reports how each attempt went. It never fails as a whole, so
+/// one unreachable node doesn't stop us from using the rest of the cluster.
+pub async fn connect_to_cluster<I: Ipfs + Sync>(ipfs: &I, peers: &[String]) -> Vec<PeerHealth> {
+    let mut health = vec![];
+    for addr in peers {
+        let start = Instant::now();
+        let result = match ipfs.connect_to(addr).await {
+            Ok(()) => Ok(start.elapsed()),
+            Err(e) => Err(e.to_string()),
+        };
+        health.push(PeerHealth {
+            addr: addr.clone(),
+            result,
+        });
+    }
+    health
+}
+
+/// This function adds the peers to Kubo's `Peering.Peers`, so the daemon keeps reconnecting to
+/// them across restarts. It returns whether the config had to change.
+pub async fn persist_peering<I: Ipfs + Sync>(ipfs: &I, peers: &[String]) -> Result<bool> {
+    let mut config = ipfs.get_full_config().await?;
+    let peering = config
+        .peering
+        .get_or_insert(None)
+        .get_or_insert_with(Default::default)
+        .peers
+        .get_or_insert(None)
+        .get_or_insert_with(Vec::new);
+
+    let mut changed = false;
+    for peer in peers {
+        // Peering.Peers keys peers by ID with the transport addresses listed separately
+        let (addr, id) = match peer.rsplit_once("/p2p/") {
+            Some(x) => x,
+            None => continue,
+        };
+        match peering.iter_mut().find(|known| known.id == id) {
+            Some(known) if known.addrs.iter().any(|known_addr| known_addr == addr) => {}
+            Some(known) => {
+                known.addrs.push(addr.to_string());
+                changed = true;
+            }
+            None => {
+                peering.push(PeeringPeer {
+                    id: id.to_string(),
+                    addrs: vec![addr.to_string()],
+                });
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        ipfs.replace_config(&config).await?;
+    }
+    Ok(changed)
+}
+
+/// This function makes sure we are connected to the cluster before uploading, with `persist`
+/// also persisting the peering so it survives daemon restarts. It fails only if none of the peers
+/// could be reached.
+pub async fn ensure_cluster_connectivity<I: Ipfs + Sync>(
+    ipfs: &I,
+    peers: &[String],
+    persist: bool,
+) -> Result<Vec<PeerHealth>> {
+    if persist {
+        persist_peering(ipfs, peers).await?;
+    }
+    let health = connect_to_cluster(ipfs, peers).await;
+    if !peers.is_empty() && health.iter().all(|peer| peer.result.is_err()) {
+        bail!("Failed to connect to any of the Fission IPFS cluster peers");
+    }
+    Ok(health)
+}
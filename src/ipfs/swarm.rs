@@ -0,0 +1,93 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+/// Which side opened a connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Unknown,
+    Inbound,
+    Outbound,
+}
+
+/// A peer in the swarm we are currently connected to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub addr: String,
+    /// The last measured round trip time, if we have measured one yet
+    pub latency: Option<Duration>,
+    pub direction: Direction,
+}
+
+/// The identity of our own node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeId {
+    pub peer_id: String,
+    pub addresses: Vec<String>,
+    pub agent_version: String,
+}
+
+impl PeerInfo {
+    /// The full multiaddr of the peer, which can be handed back to `Ipfs::connect_to`
+    pub fn multiaddr(&self) -> String {
+        format!("{}/p2p/{}", self.addr, self.peer_id)
+    }
+}
+
+impl From<i64> for Direction {
+    /// Kubo reports directions using libp2p's numbering
+    fn from(direction: i64) -> Self {
+        match direction {
+            1 => Direction::Inbound,
+            2 => Direction::Outbound,
+            _ => Direction::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Unknown => write!(f, "unknown"),
+            Direction::Inbound => write!(f, "inbound"),
+            Direction::Outbound => write!(f, "outbound"),
+        }
+    }
+}
+
+/// Parses a duration in the format Go prints them in, such as `85.2ms` or `1m2.5s`. Kubo uses this
+/// format for the latencies it reports.
+pub fn parse_go_duration(s: &str) -> Result<Duration> {
+    if s == "0" {
+        return Ok(Duration::ZERO);
+    }
+    let mut total = 0f64;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = match rest[..number_len].parse() {
+            Ok(x) => x,
+            Err(_) => bail!("Invalid duration: {}", s),
+        };
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let seconds_per_unit = match &rest[..unit_len] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => bail!("Invalid duration: {}", s),
+        };
+        rest = &rest[unit_len..];
+        total += number * seconds_per_unit;
+    }
+    Ok(Duration::from_secs_f64(total))
+}
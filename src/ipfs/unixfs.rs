@@ -0,0 +1,173 @@
+use anyhow::{bail, Context, Result};
+
+use crate::ipfs::cid::{read_varint, Cid, DAG_PB, RAW};
+use crate::ipfs::Ipfs;
+
+/// An entry of a UnixFS directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub name: String,
+    pub cid: String,
+    /// The size of the file's contents in bytes, zero for directories
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// Anything blocks can be fetched from
+pub trait BlockSource {
+    /// This method fetches a single block. It doesn't need to check that the block matches the
+    /// CID, callers do that.
+    fn fetch_block(&self, cid: &Cid) -> Result<Vec<u8>>;
+}
+
+impl<I: Ipfs + Sync> BlockSource for I {
+    fn fetch_block(&self, cid: &Cid) -> Result<Vec<u8>> {
+        futures::executor::block_on(self.get_block(&cid.to_string()))
+    }
+}
+
+/// The UnixFS node types, numbered as in the UnixFS protobuf
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeType {
+    Raw = 0,
+    Directory = 1,
+    File = 2,
+    Metadata = 3,
+    Symlink = 4,
+    HamtShard = 5,
+}
+
+/// A link from one DAG-PB node to another
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PbLink {
+    pub cid: Cid,
+    pub name: String,
+}
+
+/// A decoded UnixFS block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub node_type: NodeType,
+    /// The file contents held by this block itself, before those of its links
+    pub data: Vec<u8>,
+    pub links: Vec<PbLink>,
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// This function decodes a block, which is either a raw leaf or a DAG-PB node holding UnixFS data
+pub fn decode_block(cid: &Cid, block: &[u8]) -> Result<Node> {
+    match cid.codec {
+        RAW => Ok(Node {
+            node_type: NodeType::Raw,
+            data: block.to_vec(),
+            links: vec![],
+        }),
+        DAG_PB => decode_pb_node(block).with_context(|| format!("Invalid UnixFS node {}", cid)),
+        codec => bail!("{} is not UnixFS, its codec is {:#x}", cid, codec),
+    }
+}
+
+/// This function reads the whole contents of the file with the given CID, checking every block
+/// against its CID
+pub fn read_file<B: BlockSource>(source: &B, cid: &Cid) -> Result<Vec<u8>> {
+    let mut contents = vec![];
+    // Depth first, as a node's own data comes before that of its links
+    let mut pending = vec![cid.clone()];
+    while let Some(cid) = pending.pop() {
+        let block = source.fetch_block(&cid)?;
+        cid.verify(&block)?;
+        let node = decode_block(&cid, &block)?;
+        if !matches!(node.node_type, NodeType::Raw | NodeType::File) {
+            bail!("{} is not a file", cid);
+        }
+        contents.extend_from_slice(&node.data);
+        pending.extend(node.links.into_iter().rev().map(|link| link.cid));
+    }
+    Ok(contents)
+}
+
+fn decode_pb_node(block: &[u8]) -> Result<Node> {
+    let mut links = vec![];
+    let mut unixfs = None;
+    for (field, value) in decode_fields(block)? {
+        match (field, value) {
+            (1, Field::Bytes(data)) => unixfs = Some(data),
+            (2, Field::Bytes(link)) => {
+                let mut cid = None;
+                let mut name = String::new();
+                for (field, value) in decode_fields(link)? {
+                    match (field, value) {
+                        (1, Field::Bytes(hash)) => cid = Some(Cid::from_bytes(hash)?),
+                        (2, Field::Bytes(x)) => name = String::from_utf8(x.to_vec())?,
+                        _ => {}
+                    }
+                }
+                links.push(PbLink {
+                    cid: cid.context("Link without a hash")?,
+                    name,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut node_type = None;
+    let mut data = vec![];
+    for (field, value) in decode_fields(unixfs.context("Missing UnixFS data")?)? {
+        match (field, value) {
+            (1, Field::Varint(x)) => {
+                node_type = Some(match x {
+                    0 => NodeType::Raw,
+                    1 => NodeType::Directory,
+                    2 => NodeType::File,
+                    3 => NodeType::Metadata,
+                    4 => NodeType::Symlink,
+                    5 => NodeType::HamtShard,
+                    x => bail!("Unknown UnixFS type {}", x),
+                })
+            }
+            (2, Field::Bytes(x)) => data = x.to_vec(),
+            _ => {}
+        }
+    }
+    Ok(Node {
+        node_type: node_type.context("Missing UnixFS type")?,
+        data,
+        links,
+    })
+}
+
+/// Splits a protobuf message into its fields, skipping fixed size ones
+fn decode_fields(mut bytes: &[u8]) -> Result<Vec<(u64, Field<'_>)>> {
+    let mut fields = vec![];
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        let value = match key & 7 {
+            0 => Field::Varint(read_varint(&mut bytes)?),
+            2 => {
+                let len = read_varint(&mut bytes)? as usize;
+                if len > bytes.len() {
+                    bail!("Truncated protobuf field");
+                }
+                let (value, rest) = bytes.split_at(len);
+                bytes = rest;
+                Field::Bytes(value)
+            }
+            1 | 5 => {
+                let len = if key & 7 == 1 { 8 } else { 4 };
+                if len > bytes.len() {
+                    bail!("Truncated protobuf field");
+                }
+                bytes = &bytes[len..];
+                continue;
+            }
+            wire_type => bail!("Unsupported protobuf wire type {}", wire_type),
+        };
+        fields.push((key >> 3, value));
+    }
+    Ok(fields)
+}
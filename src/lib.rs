@@ -1,6 +1,9 @@
+pub mod app;
 pub mod cmd;
 pub mod ipfs;
 pub mod legacy;
+pub mod server;
+pub mod settings;
 pub mod utils;
 
 #[cfg(test)]
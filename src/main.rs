@@ -2,6 +2,7 @@ use clap::{Parser, Subcommand};
 use fission::cmd::{
     app::{run_command as run_app_command, App},
     generate::{run_command as run_generate_command, Generate},
+    ipfs::{run_command as run_ipfs_command, Ipfs},
     setup::run_command as run_setup_command,
     user::{run_command as run_user_command, User, UserCommands},
 };
@@ -23,6 +24,8 @@ enum Commands {
     App(App),
     #[clap(about = "Generate key pairs and DIDs")]
     Generate(Generate),
+    #[clap(about = "IPFS node management")]
+    Ipfs(Ipfs),
     #[clap(about = "Initial Fission setup")]
     Setup {
         #[clap(short, long, value_parser, help = "The username to register")]
@@ -59,12 +62,28 @@ enum Commands {
 fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::App(a) => match run_app_command(a) {
-            Ok(()) => (),
-            Err(_err) => eprintln!("💥 Failed to execute app command."),
+    let code = match cli.command {
+        Commands::App(a) => {
+            let error_code = a.error_code();
+            match run_app_command(a) {
+                Ok(outcome) => outcome.exit_code(),
+                Err(err) => {
+                    eprintln!("💥 Failed to execute app command.\n{:#}", err);
+                    error_code
+                }
+            }
+        }
+        Commands::Generate(g) => {
+            run_generate_command(g);
+            0
+        }
+        Commands::Ipfs(i) => match run_ipfs_command(i) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("💥 Failed to execute ipfs command.\n{:#}", err);
+                1
+            }
         },
-        Commands::Generate(g) => run_generate_command(g),
         Commands::Setup {
             username,
             email,
@@ -73,20 +92,32 @@ fn main() {
             verbose,
             remote,
         } => match run_setup_command(username, email, keyfile, os, verbose, remote) {
-            Ok(()) => (),
-            Err(_err) => eprintln!("💥 Failed to execute setup command."),
+            Ok(()) => 0,
+            Err(_err) => {
+                eprintln!("💥 Failed to execute setup command.");
+                1
+            }
         },
         Commands::User(u) => match run_user_command(u) {
-            Ok(()) => (),
-            Err(_err) => eprintln!("💥 Failed to execute user command.",),
+            Ok(()) => 0,
+            Err(_err) => {
+                eprintln!("💥 Failed to execute user command.",);
+                1
+            }
         },
 
         // Shortcuts
         Commands::Whoami { verbose, remote } => match run_user_command(User {
             command: UserCommands::Whoami { verbose, remote },
         }) {
-            Ok(()) => (),
-            Err(_err) => eprintln!("💥 Failed to execute whoami command.",),
+            Ok(()) => 0,
+            Err(_err) => {
+                eprintln!("💥 Failed to execute whoami command.",);
+                1
+            }
         },
-    }
+    };
+
+    // Failures exit non-zero, so scripts and CI can rely on checks like `app check`
+    std::process::exit(code);
 }
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub mod client;
+pub mod ucan;
+
+/// What the server knows about an app
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppInfo {
+    /// Every URL the app is served at, such as `my-app.fission.app`
+    #[serde(default)]
+    pub urls: Vec<String>,
+    pub cid: Option<String>,
+    /// When the app was last published, in RFC 3339 format
+    pub published_at: Option<String>,
+    /// The total size of the published files in bytes
+    pub size: Option<u64>,
+    /// The custom domains pointing at the app
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// The username or DID of the app's owner
+    pub owner: Option<String>,
+}
+
+#[async_trait]
+pub trait FissionServer {
+    /// This method returns the DID of the key requests are signed with
+    fn did(&self) -> &str;
+    /// This method creates an app, returning its URL. Without a subdomain, the server picks one.
+    async fn create_app(&self, subdomain: Option<&str>) -> Result<String>;
+    /// This method lists every app of the current user
+    async fn list_apps(&self) -> Result<Vec<AppInfo>>;
+    /// This method deletes the app at the given URL. Its published data is no longer pinned.
+    async fn destroy_app(&self, url: &str) -> Result<()>;
+    /// This method points the app at the given URL (`my-app.fission.app`) to a new CID, which
+    /// also updates its DNSLink. If `copy_data` is set, the server fetches and pins the data
+    /// from the IPFS swarm.
+    async fn update_app(&self, url: &str, cid: &str, copy_data: bool) -> Result<()>;
+    /// This method returns the CID the app at the given URL currently points to, or `None` if
+    /// nothing was published yet
+    async fn app_cid(&self, url: &str) -> Result<Option<String>>;
+    /// This method returns what the server knows about the app at the given URL, or `None` if
+    /// there is no such app
+    async fn app_info(&self, url: &str) -> Result<Option<AppInfo>>;
+}
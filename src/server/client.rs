@@ -0,0 +1,245 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::client::HttpConnector;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Body, Client, Method, Request, StatusCode};
+use hyper_tls::HttpsConnector;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::runtime::Runtime;
+
+use crate::server::ucan::{proof_potency, Potency, Resource, SigningKey};
+use crate::server::{AppInfo, FissionServer};
+use crate::settings::Settings;
+use crate::utils::config::{
+    FISSION_API_PATH, FISSION_API_URL, FISSION_LOCAL_API_URL, FISSION_STAGING_API_URL,
+};
+
+/// Characters that can appear unescaped in a URL path segment
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A client for the Fission server's HTTP API, authenticating every request with a UCAN issued
+/// by the machine key.
+pub struct ServerClient {
+    base_url: String,
+    server_did: String,
+    proof: Option<String>,
+    key: SigningKey,
+    client: Client<HttpsConnector<HttpConnector>>,
+    tokio: Runtime,
+}
+
+impl ServerClient {
+    /// This method creates a client from the keys and server DID `fission setup` saved to the
+    /// user's settings. `remote` is the value of the hidden `--remote` flag, which can be
+    /// `production`, `staging`, `local` or the URL of any other Fission server.
+    pub fn new(settings: &Settings, remote: Option<&str>) -> Result<Self> {
+        let server_did = match &settings.server_did {
+            Some(x) => x.clone(),
+            None => bail!("No Fission server DID is configured. Run `fission setup` first."),
+        };
+        let key = match &settings.signing_key_path {
+            Some(path) => SigningKey::from_file(path)?,
+            None => bail!("No signing key is configured. Run `fission setup` first."),
+        };
+        Ok(Self {
+            base_url: remote_url(remote),
+            server_did,
+            proof: settings.root_proof.clone(),
+            key,
+            client: Client::builder().build(HttpsConnector::new()),
+            tokio: Runtime::new()?,
+        })
+    }
+
+    async fn request<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+        resource: &Resource,
+        potency: Potency,
+    ) -> Result<Bytes> {
+        match self
+            .send(method.clone(), path, body, resource, potency)
+            .await?
+        {
+            (status, bytes) if status.is_success() => Ok(bytes),
+            (status, bytes) => bail!(
+                "{} {} failed ({}): {}",
+                method,
+                path,
+                status,
+                String::from_utf8_lossy(&bytes)
+            ),
+        }
+    }
+
+    /// Like `request`, but a 404 is returned as `None` rather than an error
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        resource: &Resource,
+    ) -> Result<Option<T>> {
+        match self
+            .send::<()>(Method::GET, path, None, resource, Potency::Append)
+            .await?
+        {
+            (StatusCode::NOT_FOUND, _) => Ok(None),
+            (status, bytes) if status.is_success() => Ok(Some(serde_json::from_slice(&bytes)?)),
+            (status, bytes) => bail!(
+                "GET {} failed ({}): {}",
+                path,
+                status,
+                String::from_utf8_lossy(&bytes)
+            ),
+        }
+    }
+
+    /// Sends a request authorized with a UCAN of the given potency over `resource`. Keys that were delegated to
+    /// can't issue more than their proof grants, so that is checked before anything is sent.
+    /// Proofs we can't read, such as ones in a newer UCAN format, are left to the server to judge.
+    async fn send<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+        resource: &Resource,
+        potency: Potency,
+    ) -> Result<(StatusCode, Bytes)> {
+        if let Some(Ok(granted)) = self.proof.as_deref().map(proof_potency) {
+            if granted < potency {
+                bail!(
+                    "This needs {} potency, but this machine was only delegated {}. Ask for a \
+                     new delegation with `fission app delegate --potency {}`.",
+                    potency,
+                    granted,
+                    potency.to_string().to_lowercase().replace('_', "-")
+                );
+            }
+        }
+        let uri = format!("{}{}{}", self.base_url, FISSION_API_PATH, path);
+        let token = self
+            .key
+            .ucan(&self.server_did, self.proof.as_deref(), resource, potency)?;
+        let req = Request::builder()
+            .method(method)
+            .uri(&uri)
+            .header(AUTHORIZATION, format!("Bearer {}", token));
+        let req = match body {
+            Some(body) => req
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(body)?))?,
+            None => req.body(Body::empty())?,
+        };
+
+        let res =
+            self.client.request(req).await.with_context(|| {
+                format!("Failed to reach the Fission server at {}", self.base_url)
+            })?;
+        let status = res.status();
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+        Ok((status, bytes))
+    }
+}
+
+/// Maps the `--remote` flag to the server's base URL
+pub fn remote_url(remote: Option<&str>) -> String {
+    match remote {
+        None | Some("production") => FISSION_API_URL.to_string(),
+        Some("staging") => FISSION_STAGING_API_URL.to_string(),
+        Some("local") | Some("development") => FISSION_LOCAL_API_URL.to_string(),
+        Some(url) => url.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Escapes a value for use as a single path segment
+pub fn segment(value: &str) -> String {
+    utf8_percent_encode(value, PATH_SEGMENT).to_string()
+}
+
+#[async_trait]
+impl FissionServer for ServerClient {
+    fn did(&self) -> &str {
+        self.key.did()
+    }
+
+    async fn create_app(&self, subdomain: Option<&str>) -> Result<String> {
+        let path = match subdomain {
+            Some(subdomain) => format!("/app?subdomain={}", segment(subdomain)),
+            None => "/app".to_string(),
+        };
+        let bytes = self.tokio.block_on(async {
+            self.request::<()>(
+                Method::POST,
+                &path,
+                None,
+                &Resource::all_apps(),
+                Potency::Append,
+            )
+            .await
+        })?;
+        serde_json::from_slice(&bytes).context("The server returned an invalid app URL")
+    }
+
+    async fn list_apps(&self) -> Result<Vec<AppInfo>> {
+        let apps = self.tokio.block_on(async {
+            self.get_json::<Vec<AppInfo>>("/app", &Resource::all_apps())
+                .await
+        })?;
+        Ok(apps.unwrap_or_default())
+    }
+
+    async fn destroy_app(&self, url: &str) -> Result<()> {
+        let path = format!("/app/associated/{}", segment(url));
+        self.tokio.block_on(async {
+            self.request::<()>(
+                Method::DELETE,
+                &path,
+                None,
+                &Resource::app(url),
+                Potency::Destroy,
+            )
+            .await
+            .map(|_| ())
+        })
+    }
+
+    async fn update_app(&self, url: &str, cid: &str, copy_data: bool) -> Result<()> {
+        let path = format!(
+            "/app/{}/{}?copy-data={}",
+            segment(url),
+            segment(cid),
+            copy_data
+        );
+        self.tokio.block_on(async {
+            self.request::<()>(
+                Method::PATCH,
+                &path,
+                None,
+                &Resource::app(url),
+                Potency::Append,
+            )
+            .await
+            .map(|_| ())
+        })
+    }
+
+    async fn app_cid(&self, url: &str) -> Result<Option<String>> {
+        Ok(self.app_info(url).await?.and_then(|app| app.cid))
+    }
+
+    async fn app_info(&self, url: &str) -> Result<Option<AppInfo>> {
+        // The server only lists apps, it has no route for a single one
+        let apps = self.list_apps().await?;
+        Ok(apps
+            .into_iter()
+            .find(|app| app.urls.iter().any(|app_url| app_url == url)))
+    }
+}
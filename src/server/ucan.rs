@@ -0,0 +1,180 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use base64::engine::fast_portable::{FastPortable, NO_PAD};
+use clap::ArgEnum;
+use did_key::{from_existing_key, Config, CoreSign, DIDCore, Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::config::{UCAN_LIFETIME, UCAN_VERSION};
+
+const URL_SAFE_NO_PAD: FastPortable = FastPortable::from(&base64::alphabet::URL_SAFE, NO_PAD);
+
+#[derive(Serialize)]
+struct Header<'a> {
+    alg: &'a str,
+    typ: &'a str,
+    uav: &'a str,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    iss: &'a str,
+    aud: &'a str,
+    nbf: u64,
+    exp: u64,
+    prf: Option<&'a str>,
+    ptc: &'a str,
+    rsc: &'a Resource,
+    fct: Vec<()>,
+}
+
+/// What a UCAN lets its audience act on, serialized the way the server expects, such as
+/// `{"app": "my-app.fission.app"}`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resource {
+    /// The app at a URL, or every app of the user with `*`
+    App(String),
+}
+
+impl Resource {
+    /// Every app of the user, for requests that aren't about a single one
+    pub fn all_apps() -> Self {
+        Resource::App("*".to_string())
+    }
+
+    pub fn app(url: &str) -> Self {
+        Resource::App(url.to_string())
+    }
+}
+
+/// How much a UCAN lets its audience do, from least to most
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum Potency {
+    Append,
+    Destroy,
+    SuperUser,
+}
+
+impl fmt::Display for Potency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Potency::Append => "Append",
+            Potency::Destroy => "Destroy",
+            Potency::SuperUser => "Super_User",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Potency {
+    /// The name of the potency in the `ptc` field of a UCAN
+    pub fn ptc(&self) -> &'static str {
+        match self {
+            Potency::Append => "APPEND",
+            Potency::Destroy => "DESTROY",
+            Potency::SuperUser => "SUPER_USER",
+        }
+    }
+
+    fn from_ptc(ptc: &str) -> Option<Self> {
+        [Potency::Append, Potency::Destroy, Potency::SuperUser]
+            .into_iter()
+            .find(|potency| potency.ptc() == ptc)
+    }
+}
+
+#[derive(Deserialize)]
+struct ProofPayload {
+    ptc: String,
+}
+
+/// This function reads the potency a UCAN grants, without checking its signature. It is only used
+/// to fail early with a clear message when the proof we hold can't allow a request.
+pub fn proof_potency(proof: &str) -> Result<Potency> {
+    let payload = match proof.split('.').nth(1) {
+        Some(x) => x,
+        None => bail!("The root proof is not a UCAN"),
+    };
+    let bytes = base64::decode_engine(payload, &URL_SAFE_NO_PAD)
+        .context("The root proof is not a valid UCAN")?;
+    let payload: ProofPayload =
+        serde_json::from_slice(&bytes).context("The root proof is not a valid UCAN")?;
+    Potency::from_ptc(&payload.ptc)
+        .with_context(|| format!("The root proof has an unknown potency {:?}", payload.ptc))
+}
+
+/// The machine key the legacy CLI writes during `fission setup`
+pub struct SigningKey {
+    keys: KeyPair,
+    did: String,
+}
+
+impl SigningKey {
+    /// This method reads a base64 encoded Ed25519 private key, the same format
+    /// `fission generate credentials` prints.
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the signing key at {:?}", path))?;
+        let bytes = base64::decode(contents.trim())
+            .with_context(|| format!("The signing key at {:?} is not valid base64", path))?;
+        // Some tools store the public key after the 32 byte secret, which we don't need
+        if bytes.len() != 32 && bytes.len() != 64 {
+            bail!(
+                "The signing key at {:?} is {} bytes long, but Ed25519 keys are 32 bytes, or 64 \
+                 with the public key",
+                path,
+                bytes.len()
+            );
+        }
+        let secret = &bytes[..32];
+        let keys = from_existing_key::<Ed25519KeyPair>(&[], Some(secret));
+        let did = keys.get_did_document(Config::default()).id;
+        Ok(Self { keys, did })
+    }
+
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// This method issues a short lived UCAN (a JWT signed by our key) granting the audience
+    /// the given potency over a resource, optionally backed by a proof delegated to us.
+    pub fn ucan(
+        &self,
+        audience: &str,
+        proof: Option<&str>,
+        resource: &Resource,
+        potency: Potency,
+    ) -> Result<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let header = Header {
+            alg: "EdDSA",
+            typ: "JWT",
+            uav: UCAN_VERSION,
+        };
+        let payload = Payload {
+            iss: &self.did,
+            aud: audience,
+            // Leave some room for clock drift between us and the server
+            nbf: now - 60,
+            exp: now + UCAN_LIFETIME,
+            prf: proof,
+            ptc: potency.ptc(),
+            rsc: resource,
+            fct: vec![],
+        };
+        let signed = format!(
+            "{}.{}",
+            base64::encode_engine(serde_json::to_vec(&header)?, &URL_SAFE_NO_PAD),
+            base64::encode_engine(serde_json::to_vec(&payload)?, &URL_SAFE_NO_PAD)
+        );
+        let signature = self.keys.sign(signed.as_bytes());
+        Ok(format!(
+            "{}.{}",
+            signed,
+            base64::encode_engine(signature, &URL_SAFE_NO_PAD)
+        ))
+    }
+}
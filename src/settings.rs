@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::ipfs::client::IpfsAuth;
+use crate::utils::config::{CONFIG_DIR, CONFIG_FILE};
+
+/// The user's CLI settings, read from `~/.config/fission/config.yaml`. This is the same file the
+/// legacy CLI writes, so any keys we don't know about are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub username: Option<String>,
+    /// The DID of the Fission server we issue UCANs to
+    pub server_did: Option<String>,
+    /// The machine's Ed25519 key, used to sign UCANs
+    pub signing_key_path: Option<PathBuf>,
+    /// A UCAN delegating access to this machine, if it isn't the root key
+    pub root_proof: Option<String>,
+    /// Multiaddrs of the IPFS cluster to stay connected to while publishing
+    pub peers: Vec<String>,
+    pub ipfs: IpfsSettings,
+}
+
+/// Settings for how the CLI talks to IPFS
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct IpfsSettings {
+    /// A multiaddr or URL of a Kubo RPC API to use instead of launching a local daemon
+    pub api: Option<String>,
+    pub auth: Option<IpfsAuth>,
+    /// A PEM encoded CA certificate to trust when connecting to `api`
+    pub ca_cert: Option<PathBuf>,
+    /// The Kubo binary to launch when no `api` is set
+    pub bin: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Loads the settings file, falling back to the defaults if it does not exist.
+    pub fn load() -> Result<Self> {
+        let path = config_dir().join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        serde_yaml::from_str(&contents).with_context(|| format!("Failed to parse {:?}", path))
+    }
+}
+
+/// The directory the CLI keeps its configuration and keys in
+pub fn config_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(CONFIG_DIR)
+}
@@ -1 +1,19 @@
+pub mod app_config;
+pub mod assets;
+pub mod check;
+pub mod client;
+pub mod cmd;
+pub mod config;
 pub mod daemon;
+pub mod domain;
+pub mod filter;
+pub mod history;
+pub mod manage;
+pub mod preview;
+pub mod publish;
+pub mod pull;
+pub mod register;
+pub mod scan;
+pub mod serve;
+pub mod stubs;
+pub mod watch;
@@ -0,0 +1,227 @@
+use crate::app::config::{AppConfig, ConfigFormat, Issue};
+use crate::test::stubs::copy_app;
+use crate::utils::config::APP_CONFIG_VERSION;
+
+#[test]
+fn parses_yaml_and_toml() {
+    let yaml = AppConfig::parse(
+        "version: 1\nurl: my-app.fission.app\nbuild:\n  dir: dist\n  command: npm run build\n",
+        ConfigFormat::Yaml,
+    )
+    .unwrap();
+    let toml = AppConfig::parse(
+        "version = 1\nurl = \"my-app.fission.app\"\n\n[build]\ndir = \"dist\"\ncommand = \"npm run build\"\n",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(yaml, toml);
+    assert!(yaml.ignore.is_empty());
+
+    let defaults = AppConfig::parse("url = \"my-app.fission.app\"\n", ConfigFormat::Toml).unwrap();
+    assert_eq!(defaults.version, 0);
+    assert_eq!(defaults.build.dir.to_str(), Some("."));
+}
+
+#[test]
+fn points_at_the_broken_line() {
+    let issue = AppConfig::parse(
+        "url: my-app.fission.app\nbuild: ./dist\nignore: nope\n",
+        ConfigFormat::Yaml,
+    )
+    .unwrap_err();
+    assert_eq!(issue.line, Some(3));
+
+    let issue = AppConfig::parse(
+        "url = \"a.fission.app\"\nignore = [1]\n",
+        ConfigFormat::Toml,
+    )
+    .unwrap_err();
+    assert_eq!(issue.line, Some(2));
+    assert!(!issue.message.contains("at line"));
+
+    // Mistakes in `build` are reported like those of any other table
+    let issue = AppConfig::parse(
+        "url: my-app.fission.app\nbuild:\n  command: make\n",
+        ConfigFormat::Yaml,
+    )
+    .unwrap_err();
+    assert!(issue.message.contains("missing field `dir`"), "{}", issue);
+    let issue = AppConfig::parse(
+        "url: my-app.fission.app\nbuild:\n  dir: dist\n  env: [1]\n",
+        ConfigFormat::Yaml,
+    )
+    .unwrap_err();
+    assert_eq!(issue.line, Some(4));
+    assert!(!issue.message.contains("did not match any variant"));
+
+    let contents =
+        "url: not a url\nbuild:\n  dir: dist\n  env:\n    BAD-NAME: x\nignore:\n  - \"a/{b\"\n";
+    let config = AppConfig::parse(contents, ConfigFormat::Yaml).unwrap();
+    let lines: Vec<_> = config
+        .validate(contents, ConfigFormat::Yaml)
+        .into_iter()
+        .map(|Issue { line, .. }| line)
+        .collect();
+    assert_eq!(lines, vec![Some(1), Some(5), Some(7)]);
+}
+
+#[test]
+fn migrates_legacy_configs() {
+    let app_dir = copy_app("migrates");
+    let config = AppConfig::load(&app_dir).unwrap();
+    assert_eq!(config.version, APP_CONFIG_VERSION);
+
+    let mut newer = config.clone();
+    newer.version = APP_CONFIG_VERSION + 1;
+    assert!(!newer.migrate());
+    assert_eq!(newer.version, APP_CONFIG_VERSION + 1);
+}
+
+#[test]
+fn saves_in_the_existing_format() {
+    let app_dir = copy_app("saves-toml");
+    let mut config = AppConfig::load(&app_dir).unwrap();
+    std::fs::remove_file(app_dir.join("fission.yaml")).unwrap();
+    std::fs::write(app_dir.join("fission.toml"), "").unwrap();
+    config.build.command = Some("make".to_string());
+    config.ignore = vec!["*.bak".to_string()];
+    config.save(&app_dir).unwrap();
+
+    assert_eq!(AppConfig::load(&app_dir).unwrap(), config);
+    std::fs::write(app_dir.join("fission.yaml"), "").unwrap();
+    assert!(AppConfig::load(&app_dir).is_err());
+}
+
+#[test]
+fn validates_routing_rules() {
+    let contents = "url: my-app.fission.app
+routing:
+  spa: true
+  redirects:
+    - from: /old
+      to: /new
+    - from: blog
+      to: /posts
+  headers:
+    /*:
+      Bad Name: x
+";
+    let config = AppConfig::parse(contents, ConfigFormat::Yaml).unwrap();
+    assert!(config.routing.spa);
+    assert_eq!(config.routing.redirects[0].status, 301);
+    let lines: Vec<_> = config
+        .validate(contents, ConfigFormat::Yaml)
+        .into_iter()
+        .map(|issue| issue.line)
+        .collect();
+    // The header name, rather than the path it is set for
+    assert_eq!(lines, vec![Some(7), Some(11)]);
+}
+
+#[test]
+fn reports_unknown_keys() {
+    let contents = "url: my-app.fission.app
+build:
+  dir: dist
+  comand: make
+environments:
+  staging:
+    url: staging.fission.app
+    dri: staging
+routing:
+  redirects:
+    - from: /old
+      too: /new
+  headers:
+    /*:
+      X-Frame-Options: DENY
+";
+    let issues = AppConfig::unknown_keys(contents, ConfigFormat::Yaml);
+    let issues: Vec<_> = issues
+        .into_iter()
+        .map(|Issue { line, message }| (line, message))
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            (Some(4), "Unknown setting `build.comand`".to_string()),
+            (
+                Some(8),
+                "Unknown setting `environments.staging.dri`".to_string()
+            ),
+            (
+                Some(12),
+                "Unknown setting `routing.redirects.too`".to_string()
+            ),
+        ]
+    );
+    let toml = "url = \"my-app.fission.app\"\nspa = true\n";
+    assert_eq!(
+        AppConfig::unknown_keys(toml, ConfigFormat::Toml)[0].line,
+        Some(2)
+    );
+
+    // Newer configs can have settings this CLI doesn't know about yet
+    let app_dir = copy_app("unknown-keys");
+    std::fs::write(
+        app_dir.join("fission.yaml"),
+        "url: test-app.fission.app\nbuild: ./build\nsomething_new: true\n",
+    )
+    .unwrap();
+    assert!(AppConfig::load(&app_dir).is_err());
+    std::fs::write(
+        app_dir.join("fission.yaml"),
+        format!(
+            "version: {}\nurl: test-app.fission.app\nbuild: ./build\nsomething_new: true\n",
+            APP_CONFIG_VERSION + 1
+        ),
+    )
+    .unwrap();
+    assert!(AppConfig::load(&app_dir).is_ok());
+}
+
+#[test]
+fn points_at_repeated_keys() {
+    let contents = "url: my-app.fission.app
+environments:
+  staging:
+    url: staging.fission.app
+    command: make staging
+  beta:
+    url: staging.fission.app
+    env:
+      BAD-NAME: x
+build:
+  dir: dist
+  command: \" \"
+ignore:
+  - \"*.log\"
+  - \"a/{b\"
+";
+    let config = AppConfig::parse(contents, ConfigFormat::Yaml).unwrap();
+    let lines: Vec<_> = config
+        .validate(contents, ConfigFormat::Yaml)
+        .into_iter()
+        .map(|issue| issue.line)
+        .collect();
+    assert_eq!(lines, vec![Some(12), Some(9), Some(15)]);
+
+    let contents = "url = \"my-app.fission.app\"
+ignore = [\"*.log\", \"a/{b\"]
+
+[build]
+dir = \"dist\"
+command = \" \"
+
+[environments.staging]
+url = \"staging\"
+command = \"make staging\"
+";
+    let config = AppConfig::parse(contents, ConfigFormat::Toml).unwrap();
+    let lines: Vec<_> = config
+        .validate(contents, ConfigFormat::Toml)
+        .into_iter()
+        .map(|issue| issue.line)
+        .collect();
+    assert_eq!(lines, vec![Some(6), Some(9), Some(2)]);
+}
@@ -0,0 +1,212 @@
+use crate::app::assets::{hashed_name, looks_fingerprinted, process, AssetsConfig};
+use crate::app::links::{css_references, html_references, rename_reference, resolve};
+use crate::test::stubs::temp_site;
+
+#[test]
+fn finds_references() {
+    let html = "<!-- <img src=\"commented.png\"> -->\n\
+                <link rel=stylesheet href='css/app.css'>\n\
+                <img srcset=\"a.png 1x, b.png 2x\" style=\"background: url(bg.png)\">\n\
+                <script>if (a < b) document.write('<img src=\"no.png\">')</script>\n\
+                <style>@import \"base.css\";</style>";
+    let values: Vec<_> = html_references(html)
+        .into_iter()
+        .map(|reference| (reference.value, reference.line))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ("css/app.css", 2),
+            ("a.png", 3),
+            ("b.png", 3),
+            ("bg.png", 3),
+            ("base.css", 5),
+        ]
+        .map(|(value, line)| (value.to_string(), line))
+    );
+
+    let css =
+        "/* url(skipped.png) */ @import url(\"a.css\"); .x { background: url( 'img/x.png' ) }";
+    let values: Vec<_> = css_references(css)
+        .into_iter()
+        .map(|reference| reference.value)
+        .collect();
+    assert_eq!(values, ["a.css", "img/x.png"]);
+
+    // Whitespace outside ASCII isn't CSS whitespace, so it is part of the URL
+    let values: Vec<_> = css_references("@import\u{a0}'a.css'; a { b: url(\u{a0}x.png) }")
+        .into_iter()
+        .map(|reference| reference.value)
+        .collect();
+    assert_eq!(values, ["\u{a0}x.png"]);
+}
+
+#[test]
+fn resolves_references() {
+    assert_eq!(
+        resolve("docs/index.html", "../img/a%20b.png?v=1#top").as_deref(),
+        Some("img/a b.png")
+    );
+    assert_eq!(
+        resolve("docs/index.html", "/css/app.css").as_deref(),
+        Some("css/app.css")
+    );
+    assert_eq!(
+        resolve("index.html", "../../a.png").as_deref(),
+        Some("a.png")
+    );
+    assert_eq!(resolve("index.html", "guide/").as_deref(), Some("guide/"));
+    for external in [
+        "https://example.com/a.png",
+        "//cdn.com/a.js",
+        "mailto:a@b.c",
+        "#top",
+    ] {
+        assert_eq!(resolve("index.html", external), None);
+    }
+    assert_eq!(
+        rename_reference("../img/a.png?v=1", "a.1234abcd.png"),
+        "../img/a.1234abcd.png?v=1"
+    );
+}
+
+#[test]
+fn names_fingerprinted_files() {
+    assert!(hashed_name("assets/app.css", b"body {}").starts_with("assets/app."));
+    assert!(hashed_name("assets/app.css", b"body {}").ends_with(".css"));
+    assert_ne!(hashed_name("app.css", b"a"), hashed_name("app.css", b"b"));
+    assert!(looks_fingerprinted("assets/index-4f3a2b1c.js"));
+    assert!(looks_fingerprinted("main.8f3e1a2b.chunk.js"));
+    assert!(!looks_fingerprinted("assets/application.js"));
+    assert!(!looks_fingerprinted("jquery-3.6.0.min.js"));
+}
+
+#[test]
+fn fingerprints_and_rewrites_references() {
+    let dir = temp_site(
+        "assets-fingerprint",
+        &[
+            (
+                "index.html",
+                "<link href=\"css/app.css\" rel=stylesheet><script src=\"/js/app.js?v=2\"></script>\
+                 <script src=\"js/vendor.js\"></script>",
+            ),
+            ("css/app.css", "@import 'base.css'; h1 { background: url(../img/logo.png) }"),
+            ("css/base.css", "body { margin: 0 }"),
+            ("img/logo.png", "not really a png"),
+            ("js/app.js", "console.log(1)"),
+            ("js/vendor.js", "console.log(2)"),
+        ],
+    );
+    let config = AssetsConfig {
+        fingerprint: true,
+        exclude: vec!["js/vendor.js".to_string()],
+        ..AssetsConfig::default()
+    };
+    let manifest = process(&dir, &config).unwrap();
+    let file = |path: &str| manifest[path].file.clone();
+
+    let app_js = file("js/app.js");
+    assert_ne!(app_js, "js/app.js");
+    assert!(dir.join(&app_js).exists());
+    assert!(!dir.join("js/app.js").exists());
+    assert_eq!(file("js/vendor.js"), "js/vendor.js");
+    assert_eq!(file("index.html"), "index.html");
+
+    let app_css = std::fs::read_to_string(dir.join(file("css/app.css"))).unwrap();
+    assert!(app_css.contains(&format!(
+        "'{}'",
+        file("css/base.css").trim_start_matches("css/")
+    )));
+    assert!(app_css.contains(&format!("url(../{})", file("img/logo.png"))));
+    // The stylesheet's hash covers the rewritten references
+    assert_eq!(
+        file("css/app.css"),
+        hashed_name("css/app.css", app_css.as_bytes())
+    );
+
+    let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+    assert!(index.contains(&format!("href=\"{}\"", file("css/app.css"))));
+    assert!(index.contains(&format!("src=\"/{}?v=2\"", app_js)));
+    assert!(index.contains("src=\"js/vendor.js\""));
+    assert!(dir.join("fission-assets.json").exists());
+}
+
+#[test]
+fn keeps_names_referenced_elsewhere() {
+    let dir = temp_site(
+        "assets-elsewhere",
+        &[
+            (
+                "index.html",
+                "<meta property=\"og:image\" content=\"/og.png\">\n\
+                 <link rel=manifest href=site.webmanifest>\n\
+                 <img src=og.png> <img src=icon.png> <img src=photo.png>\n\
+                 <script type=module src=main.js></script>",
+            ),
+            (
+                "site.webmanifest",
+                "{\"icons\": [{\"src\": \"/icon.png\", \"sizes\": \"192x192\"}]}",
+            ),
+            ("main.js", "import \"./chunk.js\";"),
+            ("chunk.js", "export default 1;"),
+            ("_redirects", "/logo /photo.png 302\n"),
+            ("og.png", "og"),
+            ("icon.png", "icon"),
+            ("photo.png", "photo"),
+        ],
+    );
+    let config = AssetsConfig {
+        fingerprint: true,
+        ..AssetsConfig::default()
+    };
+    let manifest = process(&dir, &config).unwrap();
+    for path in ["og.png", "icon.png", "chunk.js", "photo.png"] {
+        assert_eq!(manifest[path].file, path);
+    }
+    // Only referenced from a <script> tag, so it can be renamed
+    assert_ne!(manifest["main.js"].file, "main.js");
+}
+
+#[test]
+fn compresses_text_files() {
+    let text = "<p>Hello, world!</p>\n".repeat(100);
+    let files = [
+        ("index.html", text.as_str()),
+        // As Create React App writes it
+        ("asset-manifest.json", "{\"files\": {}}"),
+        ("tiny.css", "a{}"),
+        ("img/photo.jpg", text.as_str()),
+    ];
+    let config = AssetsConfig {
+        compress: true,
+        ..AssetsConfig::default()
+    };
+    let dir = temp_site("assets-compress", &files);
+    let manifest = process(&dir, &config).unwrap();
+
+    let index = &manifest["index.html"];
+    assert_eq!(index.size, text.len() as u64);
+    assert_eq!(index.br.as_deref(), Some("index.html.br"));
+    assert_eq!(index.gz.as_deref(), Some("index.html.gz"));
+    let gz = std::fs::read(dir.join("index.html.gz")).unwrap();
+    assert!(gz.len() < text.len());
+    // Compressing only pays off for larger files, and not for formats that already are
+    assert_eq!(manifest["tiny.css"].br, None);
+    assert_eq!(manifest["img/photo.jpg"].gz, None);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("asset-manifest.json")).unwrap(),
+        "{\"files\": {}}"
+    );
+    assert!(process(&dir, &config).is_err());
+
+    // The same files come out the same, so the CID does too
+    let again = temp_site("assets-compress-again", &files);
+    process(&again, &config).unwrap();
+    for name in ["index.html.br", "index.html.gz", "fission-assets.json"] {
+        assert_eq!(
+            std::fs::read(dir.join(name)).unwrap(),
+            std::fs::read(again.join(name)).unwrap()
+        );
+    }
+}
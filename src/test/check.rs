@@ -0,0 +1,94 @@
+use crate::app::check::{check_links, BrokenLink};
+use crate::app::publish::{publish, PublishOptions};
+use crate::app::routing::{Routing, RoutingConfig};
+use crate::test::stubs::{copy_app, StubIpfs, StubServer};
+use crate::utils::file_filter::FileFilter;
+
+fn broken(path: &str, line: usize, reference: &str) -> BrokenLink {
+    BrokenLink {
+        path: path.to_string(),
+        line,
+        reference: reference.to_string(),
+    }
+}
+
+#[test]
+fn finds_broken_links() {
+    let app_dir = copy_app("check-links");
+    let build_dir = app_dir.join("build");
+    std::fs::create_dir_all(build_dir.join("docs")).unwrap();
+    std::fs::write(build_dir.join("docs/index.html"), "<h1>Docs</h1>").unwrap();
+    std::fs::write(build_dir.join("secret.txt"), "").unwrap();
+    std::fs::write(
+        build_dir.join("about.html"),
+        "<a href=\"/\">Home</a> <a href=\"docs/\">Docs</a> <a href=\"docs\">Docs</a>\n\
+         <a href=\"https://example.com/missing.html\">Elsewhere</a> <a href=\"#top\">Top</a>\n\
+         <img src=\"img/logo.png?v=1\" alt=\"\">\n\
+         <a href=\"secret.txt\">Secret</a>\n\
+         <a href=\"/app/settings\">Settings</a>",
+    )
+    .unwrap();
+    std::fs::write(
+        build_dir.join("assets/style.css"),
+        "h1 {\n  background: url(../img/bg.png);\n}\n",
+    )
+    .unwrap();
+
+    let filter = FileFilter::new(vec!["secret.txt".to_string()], false);
+    let routing = Routing::load(&RoutingConfig::default(), &build_dir).unwrap();
+    assert_eq!(
+        check_links(&build_dir, &filter, &routing).unwrap(),
+        vec![
+            broken("about.html", 3, "img/logo.png?v=1"),
+            broken("about.html", 4, "secret.txt"),
+            broken("about.html", 5, "/app/settings"),
+            broken("assets/style.css", 2, "../img/bg.png"),
+        ]
+    );
+
+    // Paths the routing rules answer aren't broken, but assets the app's page stands in for are
+    let spa = RoutingConfig {
+        spa: true,
+        ..RoutingConfig::default()
+    };
+    std::fs::write(
+        build_dir.join("_redirects"),
+        "/img/* /assets/style.css 200\n",
+    )
+    .unwrap();
+    let routing = Routing::load(&spa, &build_dir).unwrap();
+    assert_eq!(
+        check_links(&build_dir, &filter, &routing).unwrap(),
+        vec![broken("about.html", 4, "secret.txt")]
+    );
+}
+
+#[test]
+fn refuses_to_publish_broken_links() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("check-publish");
+    std::fs::remove_file(app_dir.join("build/assets/style.css")).unwrap();
+    let mut options = PublishOptions {
+        update_data: true,
+        update_dns: true,
+        peers: vec![],
+        persist_peering: false,
+        include_hidden: false,
+        allow_sensitive: false,
+        force: false,
+        skip_build: false,
+        skip_check: false,
+        env: None,
+        preview: None,
+        verbose: false,
+    };
+    match publish(&ipfs, &server, &app_dir, &options) {
+        Ok(_) => panic!("published a broken link"),
+        Err(e) => assert!(e.to_string().contains("--skip-check")),
+    }
+    assert!(ipfs.calls.lock().unwrap().is_empty());
+
+    options.skip_check = true;
+    assert!(publish(&ipfs, &server, &app_dir, &options).is_ok());
+}
@@ -0,0 +1,48 @@
+use clap::Parser;
+
+use crate::cmd::app::{run_command, App, Outcome};
+
+/// The global flags of `fission`, which app commands read
+#[derive(Parser)]
+struct Cli {
+    #[clap(short, long, global = true)]
+    verbose: bool,
+    #[clap(short = 'R', long, global = true)]
+    remote: Option<String>,
+    #[clap(flatten)]
+    app: App,
+}
+
+fn app(args: &[&str]) -> App {
+    Cli::parse_from(std::iter::once("app").chain(args.iter().copied())).app
+}
+
+#[test]
+fn exits_non_zero_on_failure() {
+    let missing = std::env::temp_dir().join(format!("fission-no-app-{}", std::process::id()));
+    let missing = missing.to_str().unwrap();
+
+    let check = app(&["check", missing]);
+    assert_eq!(check.error_code(), 1);
+    assert!(run_command(check).is_err());
+    assert!(run_command(app(&["config", "check", "--app-dir", missing])).is_err());
+
+    // `app diff` keeps 1 for differences, like `diff`
+    assert_eq!(app(&["diff", missing]).error_code(), 2);
+    assert_eq!(Outcome::Differs.exit_code(), 1);
+    assert_eq!(Outcome::Done.exit_code(), 0);
+}
+
+#[test]
+fn accepts_legacy_register_flags() {
+    let args = [
+        "app",
+        "register",
+        "--ipfs-bin",
+        "/usr/local/bin/ipfs",
+        "--ipfs-timeout",
+        "60",
+        "--verbose",
+    ];
+    assert!(Cli::try_parse_from(args).is_ok());
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+
+use crate::ipfs::config::{diff, merge, KuboConfig};
+
+#[test]
+fn round_trips_unknown_keys() {
+    let raw = json!({
+        "Datastore": { "StorageMax": "10GB", "StorageGCWatermark": 90, "Spec": { "type": "mount" } },
+        "Bootstrap": ["/dnsaddr/bootstrap.libp2p.io/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN"],
+        "Identity": { "PeerID": "12D3KooWQ2hL9NschcJ1Suqa1TybJc2ZaacqoQMBT3ziFC7Ye2BZ" }
+    });
+    let config: KuboConfig = serde_json::from_value(raw.clone()).unwrap();
+    assert_eq!(
+        config.datastore.clone().flatten().unwrap().storage_max,
+        Some(Some("10GB".to_string()))
+    );
+    assert_eq!(serde_json::to_value(&config).unwrap(), raw);
+}
+
+#[test]
+fn round_trips_explicit_nulls() {
+    let raw = json!({
+        "Addresses": { "API": "/ip4/127.0.0.1/tcp/5001", "Announce": null },
+        "Gateway": { "RootRedirect": null, "HTTPHeaders": null, "NoFetch": false },
+        "Peering": { "Peers": null },
+        "Routing": null
+    });
+    let config: KuboConfig = serde_json::from_value(raw.clone()).unwrap();
+    assert_eq!(config.peering.clone().unwrap().unwrap().peers, Some(None));
+    assert_eq!(config.swarm, None);
+    assert_eq!(serde_json::to_value(&config).unwrap(), raw);
+}
+
+#[test]
+fn merges_and_diffs() {
+    let current = json!({
+        "Datastore": { "StorageMax": "10GB", "GCPeriod": "1h" },
+        "Bootstrap": ["/ip4/1.2.3.4/tcp/4001"]
+    });
+    let mut desired = current.clone();
+    merge(
+        &mut desired,
+        &json!({ "Datastore": { "StorageMax": "11GB" }, "Bootstrap": [], "Gateway": { "NoFetch": true } }),
+    );
+    assert_eq!(desired["Datastore"]["GCPeriod"], json!("1h"));
+
+    let changes: Vec<String> = diff(&current, &desired)
+        .into_iter()
+        .map(|change| change.path)
+        .collect();
+    assert_eq!(
+        changes,
+        vec!["Bootstrap", "Datastore.StorageMax", "Gateway"]
+    );
+}
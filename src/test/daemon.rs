@@ -12,7 +12,10 @@ use serde_json::Value;
 use serial_test::serial;
 
 use crate::ipfs::daemon::IpfsDaemon;
+use crate::ipfs::peering::{cluster_peers, persist_peering};
 use crate::ipfs::Ipfs;
+use crate::settings::Settings;
+use crate::utils::file_filter::FileFilter;
 use crate::utils::file_management;
 
 fn run_ipfs_test<T>(test: T) -> ()
@@ -67,13 +70,13 @@ fn are_files_uploaded(uploaded_paths: Vec<String>, os_paths: Vec<String>) -> boo
 fn can_add_directory() {
     let test_dir = DATA_FOLDER.to_string() + "/more-tests";
     run_ipfs_test(|ipfs| {
-        let hashes = block_on(ipfs.add(Path::new(&test_dir))).unwrap();
+        let hashes = block_on(ipfs.add(Path::new(&test_dir), &FileFilter::none())).unwrap();
         println!("{}", "Finished Hashes:".green());
         for (path, hash) in &hashes {
             println!("{}: {}", path.green(), hash.blue())
         }
 
-        let files = file_management::get_files_in(&test_dir).unwrap();
+        let files = file_management::get_files_in(&test_dir, &FileFilter::none()).unwrap();
 
         let uploaded_paths = hashes.into_iter().map(|(path, _)| path).collect();
         let os_paths = files.into_iter().map(|(path, _)| path).collect();
@@ -85,7 +88,7 @@ fn can_add_directory() {
 fn can_add_file() {
     let test_file = DATA_FOLDER.to_string() + "/test.txt";
     run_ipfs_test(|ipfs| {
-        let hashes = block_on(ipfs.add(Path::new(&test_file))).unwrap();
+        let hashes = block_on(ipfs.add(Path::new(&test_file), &FileFilter::none())).unwrap();
         println!("{}", "Finished Hashes:\n".green());
         for (path, hash) in &hashes {
             println!("{}: {}", path.green(), hash.blue())
@@ -112,9 +115,65 @@ fn can_config() {
 #[test]
 #[serial]
 fn can_connect() {
-    let test_peer = "/dns4/production-ipfs-cluster-us-east-1-node2.runfission.com/tcp/4003/wss/p2p/12D3KooWQ2hL9NschcJ1Suqa1TybJc2ZaacqoQMBT3ziFC7Ye2BZ";
+    // Set FISSION_IPFS_PEERS to run this against a local cluster instead of production
+    let test_peers = cluster_peers(&Settings::default());
     run_ipfs_test(|ipfs| {
-        let res = block_on(ipfs.connect_to(test_peer));
-        res.is_ok()
+        test_peers
+            .iter()
+            .all(|peer| block_on(ipfs.connect_to(peer)).is_ok())
+    });
+}
+#[test]
+#[serial]
+fn can_persist_peering() {
+    let test_peers = cluster_peers(&Settings::default());
+    run_ipfs_test(|ipfs| {
+        let old_config = block_on(ipfs.get_full_config()).unwrap();
+        block_on(persist_peering(ipfs, &test_peers)).unwrap();
+        let is_idempotent = !block_on(persist_peering(ipfs, &test_peers)).unwrap();
+        let peering = block_on(ipfs.get_full_config())
+            .unwrap()
+            .peering
+            .flatten()
+            .and_then(|peering| peering.peers.flatten())
+            .unwrap_or_default();
+        block_on(ipfs.replace_config(&old_config)).unwrap();
+        is_idempotent
+            && test_peers
+                .iter()
+                .all(|peer| peering.iter().any(|known| peer.ends_with(&known.id)))
+    });
+}
+#[test]
+#[serial]
+fn can_config_numbers_and_null() {
+    let test_prop = "Datastore.StorageGCWatermark";
+    run_ipfs_test(|ipfs| {
+        let old_config = block_on(ipfs.get_config(test_prop)).unwrap();
+        let old_redirect = block_on(ipfs.get_config("Gateway.RootRedirect")).unwrap();
+        block_on(ipfs.set_config(test_prop, &Value::from(80))).unwrap();
+        let new_config = block_on(ipfs.get_config(test_prop)).unwrap();
+        block_on(ipfs.set_config("Gateway.RootRedirect", &Value::Null)).unwrap();
+        block_on(ipfs.set_config("Gateway.RootRedirect", &old_redirect)).unwrap();
+        block_on(ipfs.set_config(test_prop, &old_config)).unwrap();
+        new_config == 80
+    });
+}
+#[test]
+#[serial]
+fn can_identify_and_list_peers() {
+    let test_peers = cluster_peers(&Settings::default());
+    run_ipfs_test(|ipfs| {
+        let id = block_on(ipfs.id()).unwrap();
+        for peer in &test_peers {
+            block_on(ipfs.connect_to(peer)).unwrap();
+        }
+        let connected = block_on(ipfs.get_connected()).unwrap();
+        !id.peer_id.is_empty()
+            && test_peers.iter().all(|peer| {
+                connected
+                    .iter()
+                    .any(|connected| peer.ends_with(&connected.peer_id))
+            })
     });
 }
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::app::domain::{add, check_domain, is_apex, verify, RecordKind};
+use crate::utils::dns::{join_txt, Resolver};
+
+const URL: &str = "test-app.fission.app";
+
+/// Answers from a fixed set of records, keyed by `<type> <name>`
+#[derive(Default)]
+struct StubResolver {
+    records: HashMap<String, Vec<String>>,
+}
+
+impl StubResolver {
+    fn set(&mut self, kind: &str, name: &str, value: &str) {
+        self.records
+            .entry(format!("{} {}", kind, name))
+            .or_default()
+            .push(value.to_string());
+    }
+
+    fn get(&self, kind: &str, name: &str) -> Vec<String> {
+        let key = format!("{} {}", kind, name);
+        self.records.get(&key).cloned().unwrap_or_default()
+    }
+}
+
+impl Resolver for StubResolver {
+    fn txt(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self.get("TXT", name))
+    }
+
+    fn cname(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.get("CNAME", name).into_iter().next())
+    }
+
+    fn a(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self.get("A", name))
+    }
+}
+
+#[test]
+fn prints_records_to_create() {
+    assert!(add(URL, "not a domain").is_err());
+
+    let records = add(URL, "www.example.com").unwrap();
+    let records: Vec<_> = records
+        .iter()
+        .map(|record| (record.kind, record.name.as_str(), record.value.as_str()))
+        .collect();
+    assert_eq!(
+        records,
+        vec![
+            (RecordKind::Cname, "www.example.com", URL),
+            (
+                RecordKind::Txt,
+                "_dnslink.www.example.com",
+                "dnslink=/ipns/test-app.fission.app"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn verifies_records() {
+    let mut resolver = StubResolver::default();
+    let domain = "www.example.com";
+    assert!(verify(&resolver, URL, domain).is_err());
+
+    resolver.set("CNAME", domain, "TEST-APP.fission.app");
+    assert!(verify(&resolver, URL, domain).is_err());
+
+    resolver.set(
+        "TXT",
+        "_dnslink.www.example.com",
+        "dnslink=/ipns/test-app.fission.app",
+    );
+    verify(&resolver, URL, domain).unwrap();
+}
+
+#[test]
+fn points_apex_domains_with_alias_records() {
+    assert!(is_apex("example.com"));
+    assert!(is_apex("example.co.uk"));
+    assert!(!is_apex("www.example.com"));
+    assert!(!is_apex("app.example.io"));
+
+    let mut resolver = StubResolver::default();
+    let domain = "example.com";
+    let records = add(URL, domain).unwrap();
+    assert_eq!(records[0].kind, RecordKind::Alias);
+    assert_eq!(records[0].value, URL);
+
+    resolver.set("A", URL, "203.0.113.7");
+    resolver.set("A", domain, "198.51.100.1");
+    resolver.set(
+        "TXT",
+        "_dnslink.example.com",
+        "dnslink=/ipns/test-app.fission.app",
+    );
+    assert!(verify(&resolver, URL, domain).is_err());
+
+    // Flattened by the DNS provider, so it resolves to the app's addresses
+    resolver.set("A", domain, "203.0.113.7");
+    verify(&resolver, URL, domain).unwrap();
+}
+
+#[test]
+fn parses_dns_answers() {
+    assert!(check_domain("example.com").is_ok());
+    assert!(check_domain("example").is_err());
+    assert!(check_domain("-a.example.com").is_err());
+
+    assert_eq!(
+        join_txt(r#""dnslink=/ipns/" "test-app.fission.app""#),
+        "dnslink=/ipns/test-app.fission.app"
+    );
+    assert_eq!(join_txt(r#""say \"hi\"""#), r#"say "hi""#);
+    assert_eq!(join_txt("unquoted"), "unquoted");
+}
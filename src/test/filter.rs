@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use crate::utils::file_filter::FileFilter;
+
+const IGNORE_FOLDER: &str = "./src/test/data/ignore";
+
+fn walked(filter: &FileFilter) -> Vec<String> {
+    let root = Path::new(IGNORE_FOLDER);
+    filter
+        .walk(root)
+        .unwrap()
+        .into_iter()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| {
+            let relative = entry.path().strip_prefix(root).unwrap();
+            relative.to_string_lossy().replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+fn applies_defaults_and_ignore_files() {
+    assert_eq!(
+        walked(&FileFilter::new(vec![], false)),
+        vec![
+            ".well-known/security.txt",
+            "app.js",
+            "keep.log",
+            "sub/page.html",
+        ]
+    );
+}
+
+#[test]
+fn applies_config_patterns_and_hidden_override() {
+    let filter = FileFilter::new(vec!["sub/".to_string(), "!*.map".to_string()], true);
+    assert_eq!(
+        walked(&filter),
+        vec![
+            ".hidden",
+            ".well-known/security.txt",
+            "app.js",
+            "app.js.map",
+            "keep.log",
+        ]
+    );
+}
+
+#[test]
+fn keeps_everything_when_disabled() {
+    assert_eq!(walked(&FileFilter::none()).len(), 12);
+}
@@ -0,0 +1,128 @@
+use crate::app::history::{
+    self, format_timestamp, rfc3339_timestamp, rollback, rollback_target, Deploy,
+};
+use crate::app::info::info;
+use crate::app::publish::{publish, PublishOptions};
+use crate::test::stubs::{copy_app, StubIpfs, StubServer};
+
+fn deploy(cid: &str) -> Deploy {
+    Deploy {
+        timestamp: 0,
+        url: "test-app.fission.app".to_string(),
+        cid: cid.to_string(),
+        commit: None,
+        did: "did:key:z6MkStub".to_string(),
+        size: None,
+        rollback: false,
+    }
+}
+
+#[test]
+fn formats_timestamps() {
+    assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+    assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00");
+    assert_eq!(format_timestamp(1663171523), "2022-09-14 16:05:23");
+    assert_eq!(rfc3339_timestamp(1663171523), "2022-09-14T16:05:23Z");
+}
+
+#[test]
+fn picks_rollback_targets() {
+    let history = vec![deploy("a"), deploy("b"), deploy("c"), deploy("c")];
+    assert_eq!(rollback_target(&history, None).unwrap(), "b");
+    assert_eq!(rollback_target(&history, Some("3")).unwrap(), "a");
+    assert_eq!(
+        rollback_target(&history, Some("bafyother")).unwrap(),
+        "bafyother"
+    );
+    assert!(rollback_target(&history, Some("4")).is_err());
+    assert!(rollback_target(&[deploy("a")], None).is_err());
+}
+
+#[test]
+fn rolls_back_without_uploading() {
+    let server = StubServer::default();
+    let app_dir = copy_app("rollback");
+    history::append(&app_dir, deploy("old")).unwrap();
+    history::append(&app_dir, deploy("new")).unwrap();
+    // Deploys of other environments don't count
+    let mut staging = deploy("staging");
+    staging.url = "test-app-staging.fission.app".to_string();
+    history::append(&app_dir, staging.clone()).unwrap();
+
+    let deploy = rollback(&server, &app_dir, "test-app.fission.app", None).unwrap();
+
+    assert_eq!(deploy.cid, "old");
+    assert!(deploy.rollback);
+    assert_eq!(
+        *server.calls.lock().unwrap(),
+        vec!["update test-app.fission.app old false"]
+    );
+    let history = history::load_for(&app_dir, "test-app.fission.app").unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[2].cid, "old");
+    assert_eq!(
+        history::load_for(&app_dir, &staging.url).unwrap(),
+        vec![staging]
+    );
+}
+
+#[test]
+fn records_only_deploys_that_update_the_app() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("records-deploys");
+    let mut options = PublishOptions {
+        update_data: true,
+        update_dns: false,
+        peers: vec![],
+        persist_peering: false,
+        include_hidden: false,
+        allow_sensitive: false,
+        force: false,
+        skip_build: false,
+        skip_check: false,
+        env: None,
+        preview: None,
+        verbose: false,
+    };
+    publish(&ipfs, &server, &app_dir, &options).unwrap();
+    assert!(history::load(&app_dir).unwrap().is_empty());
+
+    // Pointing the app at files that are already uploaded is still a deploy
+    options.update_dns = true;
+    publish(&ipfs, &server, &app_dir, &options).unwrap();
+    let history = history::load(&app_dir).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].cid, "cid-of-build");
+    assert!(history[0].size.is_some());
+}
+
+#[test]
+fn summarizes_app() {
+    let server = StubServer::default();
+    let app_dir = copy_app("summarizes");
+    assert!(info(&server, &app_dir).is_err());
+
+    server
+        .apps
+        .lock()
+        .unwrap()
+        .insert("test-app.fission.app".to_string(), "b".to_string());
+    let mut published = deploy("b");
+    published.timestamp = 1663171523;
+    published.size = Some(208);
+    history::append(&app_dir, deploy("a")).unwrap();
+    history::append(&app_dir, published).unwrap();
+
+    let summary = info(&server, &app_dir).unwrap();
+    assert_eq!(summary.urls, vec!["https://test-app.fission.app"]);
+    assert_eq!(summary.cid.as_deref(), Some("b"));
+    assert_eq!(
+        summary.published_at.as_deref(),
+        Some("2022-09-14T16:05:23Z")
+    );
+    assert_eq!(summary.size, Some(208));
+    assert_eq!(summary.owner.as_deref(), Some("stub-user"));
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["build_dir"], "./build");
+}
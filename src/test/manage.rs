@@ -0,0 +1,119 @@
+use std::io::Cursor;
+
+use crate::app::config::AppConfig;
+use crate::app::manage::{confirm_destroy, destroy, list, rename, resolve_url};
+use crate::server::ucan::{proof_potency, Potency, Resource, SigningKey};
+use crate::test::stubs::{copy_app, StubServer};
+
+fn server_with_apps(urls: &[&str]) -> StubServer {
+    let server = StubServer::default();
+    for url in urls {
+        server
+            .apps
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), format!("cid-of-{}", url));
+    }
+    server
+}
+
+#[test]
+fn lists_and_resolves_apps() {
+    let server = server_with_apps(&["b-app.fission.app", "a-app.fission.app"]);
+    let urls: Vec<_> = list(&server)
+        .unwrap()
+        .into_iter()
+        .map(|app| app.urls[0].clone())
+        .collect();
+    assert_eq!(urls, vec!["a-app.fission.app", "b-app.fission.app"]);
+
+    assert_eq!(resolve_url(&server, "b-app").unwrap(), "b-app.fission.app");
+    assert_eq!(
+        resolve_url(&server, "c.example.com").unwrap(),
+        "c.example.com"
+    );
+    assert!(resolve_url(&server, "c-app").is_err());
+}
+
+#[test]
+fn renames_app_and_config() {
+    let server = server_with_apps(&["test-app.fission.app"]);
+    let app_dir = copy_app("renames");
+
+    assert!(rename(&server, &app_dir, "test-app", "Bad Name").is_err());
+    let url = rename(&server, &app_dir, "test-app", "new-name").unwrap();
+    assert_eq!(url, "new-name.fission.app");
+    assert_eq!(
+        AppConfig::load(&app_dir).unwrap().url,
+        "new-name.fission.app"
+    );
+    assert!(server
+        .apps
+        .lock()
+        .unwrap()
+        .contains_key("new-name.fission.app"));
+    assert_eq!(
+        *server.calls.lock().unwrap(),
+        vec![
+            "create new-name",
+            "update new-name.fission.app cid-of-test-app.fission.app false",
+            "destroy test-app.fission.app"
+        ]
+    );
+}
+
+#[test]
+fn destroys_after_confirmation() {
+    let server = server_with_apps(&["test-app.fission.app"]);
+    let url = "test-app.fission.app";
+
+    assert!(confirm_destroy(Cursor::new("test-app\n"), url).is_err());
+    assert!(confirm_destroy(Cursor::new(""), url).is_err());
+    confirm_destroy(Cursor::new("test-app.fission.app\n"), url).unwrap();
+
+    destroy(&server, url).unwrap();
+    assert!(server.apps.lock().unwrap().is_empty());
+    assert_eq!(
+        server.calls.lock().unwrap().last().unwrap(),
+        "destroy test-app.fission.app"
+    );
+}
+
+#[test]
+fn reads_proof_potency() {
+    let ucan = |ptc: &str| {
+        let payload = base64::encode(format!(r#"{{"iss":"did:key:z6MkStub","ptc":"{}"}}"#, ptc));
+        format!("e30.{}.c2ln", payload.trim_end_matches('='))
+    };
+    assert_eq!(proof_potency(&ucan("APPEND")).unwrap(), Potency::Append);
+    assert_eq!(
+        proof_potency(&ucan("SUPER_USER")).unwrap(),
+        Potency::SuperUser
+    );
+    assert!(proof_potency(&ucan("EVERYTHING")).is_err());
+    assert!(proof_potency("not-a-ucan").is_err());
+    assert!(Potency::Append < Potency::Destroy);
+}
+
+#[test]
+fn scopes_ucans_to_apps() {
+    assert_eq!(
+        serde_json::to_value(Resource::app("test-app.fission.app")).unwrap(),
+        serde_json::json!({ "app": "test-app.fission.app" })
+    );
+    assert_eq!(
+        serde_json::to_value(Resource::all_apps()).unwrap(),
+        serde_json::json!({ "app": "*" })
+    );
+}
+
+#[test]
+fn rejects_keys_of_the_wrong_length() {
+    let dir = copy_app("short-key");
+    let path = dir.join("machine_id.ed25519");
+    std::fs::write(&path, base64::encode([7u8; 16])).unwrap();
+    match SigningKey::from_file(&path) {
+        Ok(_) => panic!("read a 16 byte key"),
+        Err(e) => assert!(e.to_string().contains("16 bytes")),
+    }
+}
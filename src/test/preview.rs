@@ -0,0 +1,158 @@
+use crate::app::config::{AppConfig, ConfigFormat};
+use crate::app::history::parse_timestamp;
+use crate::app::preview::{preview_url, previews, prune};
+use crate::app::publish::{publish, PublishOptions};
+use crate::test::stubs::{copy_app, StubIpfs, StubServer};
+
+fn config() -> AppConfig {
+    AppConfig::parse(
+        "url: my-app.fission.app
+build:
+  dir: dist
+  command: npm run build
+  env:
+    API: https://api.example.com
+    MODE: production
+environments:
+  staging:
+    url: my-app-staging.fission.app
+    env:
+      MODE: staging
+",
+        ConfigFormat::Yaml,
+    )
+    .unwrap()
+}
+
+#[test]
+fn names_previews_after_branches() {
+    assert_eq!(
+        preview_url("my-app.fission.app", "feature/Login_Page").unwrap(),
+        "my-app-preview-feature-login-page.fission.app"
+    );
+    assert_eq!(
+        preview_url("my-app.fission.app", "--fix--").unwrap(),
+        "my-app-preview-fix.fission.app"
+    );
+    let long = preview_url("my-app.fission.app", &"x".repeat(100)).unwrap();
+    assert_eq!(long.split('.').next().unwrap().len(), 63);
+    assert!(preview_url("my-app.fission.app", "///").is_err());
+}
+
+#[test]
+fn merges_environment_settings() {
+    let config = config();
+    let staging = config.for_env("staging").unwrap();
+    assert_eq!(staging.url, "my-app-staging.fission.app");
+    assert_eq!(staging.build.dir.to_str(), Some("dist"));
+    assert_eq!(staging.build.command.as_deref(), Some("npm run build"));
+    assert_eq!(staging.build.env["MODE"], "staging");
+    assert_eq!(staging.build.env["API"], "https://api.example.com");
+    assert!(config.for_env("production").is_err());
+}
+
+#[test]
+fn parses_timestamps() {
+    assert_eq!(parse_timestamp("2022-09-14T16:05:23Z"), Some(1663171523));
+    assert_eq!(
+        parse_timestamp("2022-09-14T16:05:23.123Z"),
+        Some(1663171523)
+    );
+    assert_eq!(
+        parse_timestamp("2022-09-14T18:05:23+02:00"),
+        Some(1663171523)
+    );
+    assert_eq!(parse_timestamp("2022-09-14"), None);
+}
+
+#[test]
+fn prunes_expired_previews() {
+    let server = StubServer::default();
+    for (url, published_at) in [
+        ("my-app.fission.app", Some("2020-01-01T00:00:00Z")),
+        (
+            "my-app-preview-old.fission.app",
+            Some("2020-01-01T00:00:00Z"),
+        ),
+        (
+            "my-app-preview-new.fission.app",
+            Some("2999-01-01T00:00:00Z"),
+        ),
+        ("my-app-preview-unknown.fission.app", None),
+        (
+            "other-app-preview-old.fission.app",
+            Some("2020-01-01T00:00:00Z"),
+        ),
+    ] {
+        server
+            .apps
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), format!("cid-of-{}", url));
+        if let Some(published_at) = published_at {
+            server
+                .published_at
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), published_at.to_string());
+        }
+    }
+    let config = config();
+    assert_eq!(previews(&server, &config).unwrap().len(), 3);
+
+    let pruned = prune(&server, &config, false, true).unwrap();
+    assert_eq!(pruned, vec!["my-app-preview-old.fission.app"]);
+    assert!(server.calls.lock().unwrap().is_empty());
+
+    prune(&server, &config, false, false).unwrap();
+    assert_eq!(
+        *server.calls.lock().unwrap(),
+        vec!["destroy my-app-preview-old.fission.app"]
+    );
+    let pruned = prune(&server, &config, true, false).unwrap();
+    assert_eq!(
+        pruned,
+        vec![
+            "my-app-preview-new.fission.app",
+            "my-app-preview-unknown.fission.app"
+        ]
+    );
+}
+
+#[test]
+fn publishes_previews_to_new_apps() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("previews");
+    let options = PublishOptions {
+        update_data: true,
+        update_dns: true,
+        peers: vec![],
+        persist_peering: false,
+        include_hidden: false,
+        allow_sensitive: false,
+        force: false,
+        skip_build: false,
+        skip_check: false,
+        env: None,
+        preview: Some("feature/x".to_string()),
+        verbose: false,
+    };
+    let published = publish(&ipfs, &server, &app_dir, &options).unwrap();
+
+    assert_eq!(
+        published.url,
+        "https://test-app-preview-feature-x.fission.app"
+    );
+    assert_eq!(
+        *server.calls.lock().unwrap(),
+        vec![
+            "create test-app-preview-feature-x",
+            "update test-app-preview-feature-x.fission.app cid-of-build true"
+        ]
+    );
+    assert_eq!(
+        AppConfig::load(&app_dir).unwrap().url,
+        "test-app.fission.app"
+    );
+}
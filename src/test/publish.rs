@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::app::config::{AppConfig, BuildConfig};
+use crate::app::diff::{AppDiff, ChangeKind, FileChange};
+use crate::app::plan::plan;
+use crate::app::publish::{publish, PublishOptions};
+use crate::ipfs::unixfs::Link;
+use crate::test::stubs::{copy_app, StubIpfs, StubServer};
+
+const APP_FOLDER: &str = "./src/test/data/app";
+
+fn options(update_data: bool, update_dns: bool) -> PublishOptions {
+    PublishOptions {
+        update_data,
+        update_dns,
+        peers: vec!["/ip4/127.0.0.1/tcp/4001/p2p/12D3KooWStub".to_string()],
+        persist_peering: true,
+        include_hidden: false,
+        allow_sensitive: false,
+        force: false,
+        skip_build: false,
+        skip_check: false,
+        env: None,
+        preview: None,
+        verbose: false,
+    }
+}
+
+#[test]
+fn publishes_build_directory() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("publishes");
+    let published = publish(&ipfs, &server, &app_dir, &options(true, true)).unwrap();
+
+    assert_eq!(published.cid, "cid-of-build");
+    assert_eq!(published.url, "https://test-app.fission.app");
+    let calls = ipfs.calls.lock().unwrap();
+    assert_eq!(calls[0], "connect /ip4/127.0.0.1/tcp/4001/p2p/12D3KooWStub");
+    assert!(calls[1].starts_with("add "));
+    assert_eq!(
+        *server.calls.lock().unwrap(),
+        vec!["update test-app.fission.app cid-of-build true"]
+    );
+    assert!(ipfs.config.lock().unwrap().peering.is_some());
+}
+
+#[test]
+fn leaves_the_config_of_remote_nodes_alone() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("publishes-remote");
+    let options = PublishOptions {
+        persist_peering: false,
+        ..options(true, true)
+    };
+    publish(&ipfs, &server, &app_dir, &options).unwrap();
+
+    assert_eq!(
+        ipfs.calls.lock().unwrap()[0],
+        "connect /ip4/127.0.0.1/tcp/4001/p2p/12D3KooWStub"
+    );
+    assert!(ipfs.config.lock().unwrap().peering.is_none());
+}
+
+#[test]
+fn skips_disabled_steps() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    publish(
+        &ipfs,
+        &server,
+        Path::new(APP_FOLDER),
+        &options(false, false),
+    )
+    .unwrap();
+
+    let calls = ipfs.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].starts_with("hash "));
+    assert!(server.calls.lock().unwrap().is_empty());
+}
+
+#[test]
+fn plans_without_publishing() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    server
+        .apps
+        .lock()
+        .unwrap()
+        .insert("test-app.fission.app".to_string(), "old-cid".to_string());
+    ipfs.published.lock().unwrap().insert(
+        "old-cid".to_string(),
+        vec![
+            Link {
+                name: "index.html".to_string(),
+                cid: "cid-of-build/index.html".to_string(),
+                size: 187,
+                is_dir: false,
+            },
+            Link {
+                name: "old.js".to_string(),
+                cid: "cid-of-old.js".to_string(),
+                size: 10,
+                is_dir: false,
+            },
+        ],
+    );
+
+    let plan = plan(
+        Some(&ipfs),
+        &server,
+        Path::new(APP_FOLDER),
+        &options(true, true),
+    )
+    .unwrap();
+
+    assert_eq!(plan.cid.as_deref(), Some("cid-of-build"));
+    assert_eq!(plan.current_cid.as_deref(), Some("old-cid"));
+    assert!(plan.update_dns);
+    assert_eq!(plan.file_count, 2);
+    let changes: Vec<_> = plan
+        .changes
+        .unwrap()
+        .into_iter()
+        .map(|change| (change.path, change.kind))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            ("assets/style.css".to_string(), ChangeKind::Added),
+            ("old.js".to_string(), ChangeKind::Removed),
+        ]
+    );
+    assert!(ipfs
+        .calls
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|call| !call.starts_with("add ")));
+    assert!(server.calls.lock().unwrap().is_empty());
+}
+
+#[test]
+fn plans_without_side_effects() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("plan-staged");
+    let mut config = AppConfig::load(&app_dir).unwrap();
+    config.routing.spa = true;
+    config.save(&app_dir).unwrap();
+
+    let hashed = plan(Some(&ipfs), &server, &app_dir, &options(true, true)).unwrap();
+    assert_eq!(hashed.cid.as_deref(), Some("cid-of-build"));
+    assert!(!app_dir.join(".fission").exists());
+    let calls = ipfs.calls.lock().unwrap().clone();
+    assert!(calls[0].starts_with("hash ") && !calls[0].contains(".fission"));
+
+    // Without a running node the files aren't hashed, but the rest of the plan is there
+    let unhashed = plan(None::<&StubIpfs>, &server, &app_dir, &options(true, true)).unwrap();
+    assert_eq!(unhashed.cid, None);
+    assert!(unhashed.changes.is_none());
+    assert!(unhashed.file_count > 0);
+    assert_eq!(*ipfs.calls.lock().unwrap(), calls);
+}
+
+#[test]
+fn skips_unchanged_uploads() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("skips-unchanged");
+    let uploads = |ipfs: &StubIpfs| {
+        let calls = ipfs.calls.lock().unwrap();
+        calls.iter().filter(|call| call.starts_with("add ")).count()
+    };
+
+    publish(&ipfs, &server, &app_dir, &options(true, true)).unwrap();
+    let published = publish(&ipfs, &server, &app_dir, &options(true, true)).unwrap();
+    assert_eq!(uploads(&ipfs), 1);
+    assert_eq!(published.cid, "cid-of-build");
+    assert_eq!(
+        server.calls.lock().unwrap().last().unwrap(),
+        "update test-app.fission.app cid-of-build false"
+    );
+
+    let mut forced = options(true, false);
+    forced.force = true;
+    publish(&ipfs, &server, &app_dir, &forced).unwrap();
+    assert_eq!(uploads(&ipfs), 2);
+
+    std::fs::write(app_dir.join("build/index.html"), "<h1>Changed</h1>").unwrap();
+    publish(&ipfs, &server, &app_dir, &options(true, false)).unwrap();
+    assert_eq!(uploads(&ipfs), 3);
+}
+
+#[test]
+fn diffs_against_published_app() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = Path::new(APP_FOLDER);
+    let config = AppConfig::load(app_dir).unwrap();
+
+    let diff = AppDiff::compute(&ipfs, &server, app_dir, &config, false).unwrap();
+    assert_eq!(diff.current_cid, None);
+    assert_eq!(diff.changes(&ipfs).unwrap().len(), 2);
+
+    server
+        .apps
+        .lock()
+        .unwrap()
+        .insert("test-app.fission.app".to_string(), "old-cid".to_string());
+    ipfs.published.lock().unwrap().insert(
+        "old-cid".to_string(),
+        vec![
+            Link {
+                name: "index.html".to_string(),
+                cid: "cid-of-old-index.html".to_string(),
+                size: 200,
+                is_dir: false,
+            },
+            Link {
+                name: "assets".to_string(),
+                cid: "cid-of-build/assets".to_string(),
+                size: 0,
+                is_dir: true,
+            },
+        ],
+    );
+    ipfs.published.lock().unwrap().insert(
+        "cid-of-build/assets".to_string(),
+        vec![Link {
+            name: "style.css".to_string(),
+            cid: "cid-of-build/assets/style.css".to_string(),
+            size: 25,
+            is_dir: false,
+        }],
+    );
+
+    let diff = AppDiff::compute(&ipfs, &server, app_dir, &config, false).unwrap();
+    let changes = diff.changes(&ipfs).unwrap();
+    assert_eq!(
+        changes,
+        vec![FileChange {
+            path: "index.html".to_string(),
+            kind: ChangeKind::Changed,
+            old_size: Some(200),
+            new_size: Some(183),
+        }]
+    );
+    assert_eq!(changes[0].describe(), "index.html (200 B → 183 B, -17 B)");
+
+    server.apps.lock().unwrap().insert(
+        "test-app.fission.app".to_string(),
+        "cid-of-build".to_string(),
+    );
+    let diff = AppDiff::compute(&ipfs, &server, app_dir, &config, false).unwrap();
+    assert!(diff.changes(&ipfs).unwrap().is_empty());
+}
+
+#[test]
+fn runs_build_command_first() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("runs-build");
+    std::fs::write(
+        app_dir.join("fission.yaml"),
+        "url: test-app.fission.app\n\
+         build:\n  dir: ./dist\n  command: mkdir dist && echo \"$GREETING\" > dist/index.html\n  \
+         env:\n    GREETING: hello\n",
+    )
+    .unwrap();
+
+    let published = publish(&ipfs, &server, &app_dir, &options(true, false)).unwrap();
+    assert_eq!(published.cid, "cid-of-dist");
+    assert_eq!(
+        std::fs::read_to_string(app_dir.join("dist/index.html")).unwrap(),
+        "hello\n"
+    );
+
+    std::fs::write(
+        app_dir.join("fission.yaml"),
+        "url: test-app.fission.app\nbuild:\n  dir: ./dist\n  command: exit 3\n",
+    )
+    .unwrap();
+    assert!(publish(&ipfs, &server, &app_dir, &options(true, false)).is_err());
+    let mut skipped = options(true, false);
+    skipped.skip_build = true;
+    skipped.force = true;
+    assert!(publish(&ipfs, &server, &app_dir, &skipped).is_ok());
+}
+
+#[test]
+fn reads_both_build_forms() {
+    let short: AppConfig = serde_yaml::from_str("url: a.fission.app\nbuild: ./dist\n").unwrap();
+    assert_eq!(short.build, BuildConfig::dir("./dist"));
+    assert_eq!(
+        serde_yaml::to_string(&short).unwrap(),
+        "version: 0\nurl: a.fission.app\nbuild: ./dist\nignore: []\n"
+    );
+
+    let full: AppConfig = serde_yaml::from_str(
+        "url: a.fission.app\nbuild:\n  dir: ./dist\n  command: npm run build\n",
+    )
+    .unwrap();
+    assert_eq!(full.build.command.as_deref(), Some("npm run build"));
+    assert!(full.build.env.is_empty());
+    let round_trip: AppConfig =
+        serde_yaml::from_str(&serde_yaml::to_string(&full).unwrap()).unwrap();
+    assert_eq!(round_trip, full);
+}
+
+#[test]
+fn publishes_routing_rules() {
+    let ipfs = StubIpfs::default();
+    let server = StubServer::default();
+    let app_dir = copy_app("routing");
+    let mut config = AppConfig::load(&app_dir).unwrap();
+    config.routing.spa = true;
+    config.routing.headers.insert(
+        "/*".to_string(),
+        BTreeMap::from([("X-Frame-Options".to_string(), "DENY".to_string())]),
+    );
+    config.save(&app_dir).unwrap();
+    let own_rules = "/old /index.html 302\n";
+    std::fs::write(app_dir.join("build/_redirects"), own_rules).unwrap();
+    std::fs::write(app_dir.join("build/_headers"), "/*\n  X-Robots-Tag: none\n").unwrap();
+
+    let published = publish(&ipfs, &server, &app_dir, &options(true, false)).unwrap();
+    assert_eq!(published.cid, "cid-of-build");
+    let staged = app_dir.join(".fission/stage/build");
+    let read = |name: &str| std::fs::read_to_string(staged.join(name)).unwrap();
+    assert_eq!(
+        read("_redirects"),
+        "/old /index.html 302\n/* /index.html 200\n"
+    );
+    // The gateway doesn't read `_headers`, so neither the config's headers nor the build
+    // directory's own file are published
+    assert!(!staged.join("_headers").exists());
+    assert_eq!(read("index.html"), read("../../../build/index.html"));
+    assert!(ipfs
+        .calls
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|call| call.starts_with("add ") && call.ends_with("stage/build")));
+
+    // The build directory itself is left alone
+    assert_eq!(
+        std::fs::read_to_string(app_dir.join("build/_redirects")).unwrap(),
+        own_rules
+    );
+    assert!(app_dir.join("build/_headers").exists());
+
+    std::fs::write(app_dir.join("build/_redirects"), "/old /new 418\n").unwrap();
+    match publish(&ipfs, &server, &app_dir, &options(true, false)) {
+        Ok(_) => panic!("published an invalid _redirects file"),
+        Err(e) => assert!(format!("{:#}", e).contains("_redirects")),
+    }
+}
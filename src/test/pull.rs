@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::app::pull::{pull, pull_or_else};
+use crate::ipfs::cid::{write_varint, Cid, DAG_PB, RAW};
+use crate::test::stubs::StubIpfs;
+
+const EMPTY_DIR_V0: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+const EMPTY_DIR_V1: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+
+fn field(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+    write_varint(out, (number << 3) | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Encodes a DAG-PB node holding UnixFS data
+fn pb_node(node_type: u64, data: &[u8], links: &[(&str, &Cid)]) -> Vec<u8> {
+    let mut block = vec![];
+    for (name, cid) in links {
+        let mut link = vec![];
+        field(&mut link, 1, &cid.to_bytes());
+        field(&mut link, 2, name.as_bytes());
+        field(&mut block, 2, &link);
+    }
+    let mut unixfs = vec![0x08];
+    write_varint(&mut unixfs, node_type);
+    if !data.is_empty() {
+        field(&mut unixfs, 2, data);
+    }
+    field(&mut block, 1, &unixfs);
+    block
+}
+
+fn store(blocks: &mut HashMap<String, Vec<u8>>, codec: u64, block: Vec<u8>) -> Cid {
+    let cid = Cid::for_block(codec, &block);
+    blocks.insert(cid.to_string(), block);
+    cid
+}
+
+#[test]
+fn parses_and_verifies_cids() {
+    let cid: Cid = EMPTY_DIR_V0.parse().unwrap();
+    assert_eq!(cid.to_string(), EMPTY_DIR_V0);
+    let v1 = Cid { version: 1, ..cid };
+    assert_eq!(v1.to_string(), EMPTY_DIR_V1);
+    assert_eq!(EMPTY_DIR_V1.parse::<Cid>().unwrap(), v1);
+
+    let empty_dir = pb_node(1, &[], &[]);
+    v1.verify(&empty_dir).unwrap();
+    assert!(v1.verify(b"something else").is_err());
+}
+
+#[test]
+fn pulls_directories_and_chunked_files() {
+    let ipfs = StubIpfs::default();
+    let mut blocks = HashMap::new();
+    let index = store(&mut blocks, RAW, b"<h1>Hello</h1>".to_vec());
+    let first = store(&mut blocks, RAW, b"body { ".to_vec());
+    let second = store(&mut blocks, RAW, b"color: red }".to_vec());
+    let style = store(
+        &mut blocks,
+        DAG_PB,
+        pb_node(2, &[], &[("", &first), ("", &second)]),
+    );
+    let assets = store(
+        &mut blocks,
+        DAG_PB,
+        pb_node(1, &[], &[("style.css", &style)]),
+    );
+    let root = store(
+        &mut blocks,
+        DAG_PB,
+        pb_node(1, &[], &[("assets", &assets), ("index.html", &index)]),
+    );
+    *ipfs.blocks.lock().unwrap() = blocks;
+
+    let dest = std::env::temp_dir().join(format!("fission-pull-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dest);
+    let stats = pull(&ipfs, &root, &dest).unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.blocks, 6);
+    assert_eq!(
+        std::fs::read_to_string(dest.join("index.html")).unwrap(),
+        "<h1>Hello</h1>"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dest.join("assets/style.css")).unwrap(),
+        "body { color: red }"
+    );
+    // Pulling into a directory that isn't empty is refused
+    assert!(pull(&ipfs, &root, &dest).is_err());
+}
+
+#[test]
+fn rejects_tampered_blocks() {
+    let ipfs = StubIpfs::default();
+    let mut blocks = HashMap::new();
+    let file = store(&mut blocks, RAW, b"original".to_vec());
+    blocks.insert(file.to_string(), b"tampered".to_vec());
+    let escape = store(&mut blocks, DAG_PB, pb_node(1, &[], &[("..", &file)]));
+    *ipfs.blocks.lock().unwrap() = blocks;
+
+    let dest = std::env::temp_dir().join(format!("fission-tampered-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dest);
+    assert!(pull(&ipfs, &file, &dest.join("file")).is_err());
+    assert!(pull(&ipfs, &escape, &dest.join("escape")).is_err());
+}
+
+#[test]
+fn falls_back_when_blocks_are_missing() {
+    let mut blocks = HashMap::new();
+    let index = store(&mut blocks, RAW, b"<h1>Hello</h1>".to_vec());
+    let style = store(&mut blocks, RAW, b"body {}".to_vec());
+    let root = store(
+        &mut blocks,
+        DAG_PB,
+        pb_node(1, &[], &[("index.html", &index), ("style.css", &style)]),
+    );
+    // The daemon has part of the tree, so it writes a file before failing
+    let daemon = StubIpfs::default();
+    *daemon.blocks.lock().unwrap() = blocks.clone();
+    daemon.blocks.lock().unwrap().remove(&style.to_string());
+    let gateway = StubIpfs::default();
+    *gateway.blocks.lock().unwrap() = blocks;
+
+    let dest = std::env::temp_dir().join(format!("fission-fallback-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dest);
+    std::fs::create_dir_all(&dest).unwrap();
+    let stats = pull_or_else(&daemon, || Ok(gateway), &root, &dest).unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(
+        std::fs::read_to_string(dest.join("style.css")).unwrap(),
+        "body {}"
+    );
+}
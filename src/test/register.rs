@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use crate::app::config::AppConfig;
+use crate::app::detect::{detect, Framework};
+use crate::app::register::{check_name, register, suggest_name, RegisterOptions};
+use crate::test::stubs::{temp_site, StubServer};
+
+#[test]
+fn detects_frameworks() {
+    let cases = [
+        (
+            r#"{"devDependencies": {"@sveltejs/kit": "1", "@sveltejs/adapter-static": "1", "vite": "3"}, "scripts": {"build": "vite build"}}"#,
+            Framework::SvelteKit,
+            "build",
+        ),
+        (
+            r#"{"dependencies": {"next": "13", "react": "18"}}"#,
+            Framework::Next,
+            "out",
+        ),
+        (
+            r#"{"dependencies": {"react-scripts": "5"}}"#,
+            Framework::CreateReactApp,
+            "build",
+        ),
+        (
+            r#"{"devDependencies": {"vite": "3"}}"#,
+            Framework::Vite,
+            "dist",
+        ),
+    ];
+    for (i, (package, framework, build_dir)) in cases.iter().enumerate() {
+        let dir = temp_site(&format!("detect-{}", i), &[("package.json", package)]);
+        let detected = detect(&dir).unwrap();
+        assert_eq!(detected.framework, *framework);
+        assert_eq!(detected.build_dir, Path::new(build_dir));
+    }
+
+    let dir = temp_site(
+        "detect-yarn",
+        &[
+            (
+                "package.json",
+                r#"{"devDependencies": {"vite": "3"}, "scripts": {"build": "vite build"}}"#,
+            ),
+            ("yarn.lock", ""),
+        ],
+    );
+    assert_eq!(
+        detect(&dir).unwrap().build_command.as_deref(),
+        Some("yarn run build")
+    );
+
+    let dir = temp_site("detect-elm", &[("elm.json", "{}"), ("src/Main.elm", "")]);
+    assert_eq!(detect(&dir).unwrap().framework, Framework::Elm);
+
+    let dir = temp_site("detect-html", &[("public/index.html", "")]);
+    let detected = detect(&dir).unwrap();
+    assert_eq!(detected.framework, Framework::PlainHtml);
+    assert_eq!(detected.build_dir, Path::new("public"));
+    assert_eq!(detected.build_command, None);
+
+    let dir = temp_site(
+        "detect-kit",
+        &[(
+            "package.json",
+            r#"{"devDependencies": {"@sveltejs/kit": "1", "vite": "3"}}"#,
+        )],
+    );
+    assert_eq!(detect(&dir), None);
+}
+
+#[test]
+fn checks_and_suggests_names() {
+    assert!(check_name("my-app-2").is_ok());
+    assert!(check_name("").is_err());
+    assert!(check_name("-app").is_err());
+    assert!(check_name("My_App").is_err());
+    assert!(check_name(&"a".repeat(64)).is_err());
+
+    let dir = temp_site("My Cool_App!", &[]);
+    let suggested = suggest_name(&dir).unwrap();
+    assert!(suggested.starts_with("fission-my-cool-app-"));
+    assert!(check_name(&suggested).is_ok());
+}
+
+#[test]
+fn registers_detected_app() {
+    let server = StubServer::default();
+    let dir = temp_site(
+        "register",
+        &[(
+            "package.json",
+            r#"{"devDependencies": {"vite": "3"}, "scripts": {"build": "vite build"}}"#,
+        )],
+    );
+    let options = RegisterOptions {
+        build_dir: None,
+        name: Some("my-app".to_string()),
+    };
+
+    let config = register(&server, &dir, &options).unwrap();
+    assert_eq!(config.url, "my-app.fission.app");
+    assert_eq!(config.build.dir, Path::new("dist"));
+    assert_eq!(config.build.command.as_deref(), Some("npm run build"));
+    assert_eq!(AppConfig::load(&dir).unwrap(), config);
+    assert_eq!(*server.calls.lock().unwrap(), vec!["create my-app"]);
+
+    assert!(register(&server, &dir, &options).is_err());
+    let invalid = RegisterOptions {
+        build_dir: Some(PathBuf::from("dist")),
+        name: Some("Not Valid".to_string()),
+    };
+    assert!(register(&server, &temp_site("register-invalid", &[]), &invalid).is_err());
+}
@@ -0,0 +1,202 @@
+use crate::app::routing::{match_pattern, parse_headers, parse_redirects, RoutingConfig};
+use crate::app::serve::{cid_host, content_type, respond, DirSite, IpfsSite, SiteResponse};
+use crate::ipfs::cid::{Cid, DAG_PB, RAW};
+use crate::ipfs::unixfs::Link;
+use crate::test::stubs::{temp_site, StubIpfs};
+
+fn site(name: &str, files: &[(&str, &str)]) -> DirSite {
+    DirSite {
+        root: temp_site(name, files),
+    }
+}
+
+fn header<'a>(res: &'a SiteResponse, name: &str) -> Option<&'a str> {
+    res.headers
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn parses_redirects() {
+    let rules = parse_redirects(
+        "# Comments and blank lines are skipped\n\n/old /new\n/blog/:year/* /posts/:year/:splat 302\n/* /index.html 200\n",
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].status, 301);
+    assert_eq!(
+        rules[1].apply("/blog/2022/hello/world").as_deref(),
+        Some("/posts/2022/hello/world")
+    );
+    assert_eq!(rules[1].apply("/blog"), None);
+    assert_eq!(rules[2].apply("/").as_deref(), Some("/index.html"));
+
+    for (contents, line) in [
+        ("/a /b\nold /new\n", 2),
+        ("/a /b 418\n", 1),
+        ("/a /b 301!\n", 1),
+        ("/a /b 301 extra\n", 1),
+        ("/a/*/b /c\n", 1),
+        ("/a https://example.com 200\n", 1),
+    ] {
+        assert_eq!(parse_redirects(contents).unwrap_err().line, Some(line));
+    }
+}
+
+#[test]
+fn parses_headers() {
+    let rules = parse_headers(
+        "/*\n  X-Frame-Options: DENY\n\n/assets/*\n  Cache-Control: max-age=31536000\n",
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(
+        rules[1].headers,
+        vec![("Cache-Control".to_string(), "max-age=31536000".to_string())]
+    );
+    assert!(parse_headers("  X-Frame-Options: DENY\n").is_err());
+    assert!(parse_headers("/*\n  Not a header\n").is_err());
+    assert!(parse_headers("/*\n  Bad Name: x\n").is_err());
+}
+
+#[test]
+fn matches_patterns() {
+    assert!(match_pattern("/about", "/about/").is_some());
+    assert!(match_pattern("/about", "/about/team").is_none());
+    assert!(match_pattern("/users/:id", "/users/").is_none());
+    assert_eq!(
+        match_pattern("/users/:id", "/users/42").unwrap()["id"],
+        "42"
+    );
+    assert_eq!(match_pattern("/*", "/").unwrap()["splat"], "");
+}
+
+#[test]
+fn serves_files_and_indexes() {
+    let site = site(
+        "serve-files",
+        &[
+            ("index.html", "<h1>Home</h1>"),
+            ("docs/index.html", "<h1>Docs</h1>"),
+            ("assets/app.js", "console.log(1)"),
+        ],
+    );
+    let res = respond(&site, "/", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body, b"<h1>Home</h1>");
+    assert_eq!(
+        header(&res, "Content-Type"),
+        Some("text/html; charset=utf-8")
+    );
+
+    let res = respond(&site, "/docs", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 301);
+    assert_eq!(header(&res, "Location"), Some("/docs/"));
+    assert_eq!(
+        respond(&site, "/docs/", &RoutingConfig::default())
+            .unwrap()
+            .body,
+        b"<h1>Docs</h1>"
+    );
+
+    let res = respond(&site, "/assets/app.js", &RoutingConfig::default()).unwrap();
+    assert_eq!(
+        header(&res, "Content-Type"),
+        Some("text/javascript; charset=utf-8")
+    );
+    assert_eq!(
+        respond(&site, "/missing", &RoutingConfig::default())
+            .unwrap()
+            .status,
+        404
+    );
+    assert_eq!(
+        respond(&site, "/../secret", &RoutingConfig::default())
+            .unwrap()
+            .status,
+        400
+    );
+}
+
+#[test]
+fn applies_redirects_and_headers() {
+    let site = site(
+        "serve-rules",
+        &[
+            ("index.html", "<h1>Home</h1>"),
+            ("posts/hello.html", "<h1>Hello</h1>"),
+            ("docs/404.html", "<h1>No such doc</h1>"),
+            (
+                "_redirects",
+                "/old /posts/hello.html 302\n/blog/:slug /posts/:slug.html 200\n",
+            ),
+            ("_headers", "/*\n  X-Frame-Options: DENY\n"),
+        ],
+    );
+    let res = respond(&site, "/old", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 302);
+    assert_eq!(header(&res, "Location"), Some("/posts/hello.html"));
+    assert_eq!(header(&res, "X-Frame-Options"), Some("DENY"));
+
+    let res = respond(&site, "/blog/hello", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body, b"<h1>Hello</h1>");
+
+    let res = respond(&site, "/docs/missing", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 404);
+    assert_eq!(res.body, b"<h1>No such doc</h1>");
+
+    assert_eq!(
+        respond(&site, "/app/route", &RoutingConfig::default())
+            .unwrap()
+            .status,
+        404
+    );
+    let spa = RoutingConfig {
+        spa: true,
+        ..RoutingConfig::default()
+    };
+    let res = respond(&site, "/app/route", &spa).unwrap();
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body, b"<h1>Home</h1>");
+}
+
+#[test]
+fn serves_from_ipfs() {
+    let ipfs = StubIpfs::default();
+    let root = Cid::for_block(DAG_PB, b"root");
+    let file = Cid::for_block(RAW, b"<h1>From IPFS</h1>");
+    ipfs.published.lock().unwrap().insert(
+        root.to_string(),
+        vec![Link {
+            name: "index.html".to_string(),
+            cid: file.to_string(),
+            size: 18,
+            is_dir: false,
+        }],
+    );
+    ipfs.blocks
+        .lock()
+        .unwrap()
+        .insert(file.to_string(), b"<h1>From IPFS</h1>".to_vec());
+
+    let site = IpfsSite::new(&ipfs, root.clone());
+    let res = respond(&site, "/", &RoutingConfig::default()).unwrap();
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body, b"<h1>From IPFS</h1>");
+    assert_eq!(
+        respond(&site, "/index.html/nested", &RoutingConfig::default())
+            .unwrap()
+            .status,
+        404
+    );
+    assert_eq!(cid_host(&root), format!("{}.ipfs.localhost", root));
+}
+
+#[test]
+fn guesses_content_types() {
+    assert_eq!(content_type("a/b.SVG"), "image/svg+xml");
+    assert_eq!(content_type("app.wasm"), "application/wasm");
+    assert_eq!(content_type("v1.0/LICENSE"), "application/octet-stream");
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::ipfs::config::KuboConfig;
+use crate::ipfs::swarm::{NodeId, PeerInfo};
+use crate::ipfs::unixfs::Link;
+use crate::ipfs::Ipfs;
+use crate::server::{AppInfo, FissionServer};
+use crate::utils::file_filter::FileFilter;
+
+/// This function copies the app in `src/test/data/app` to a fresh temporary directory, so tests
+/// can publish it without leaving state behind in the repository
+pub fn copy_app(name: &str) -> PathBuf {
+    let source = Path::new("./src/test/data/app");
+    let target = std::env::temp_dir().join(format!("fission-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&target);
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry.unwrap();
+        let path = target.join(entry.path().strip_prefix(source).unwrap());
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(path).unwrap();
+        } else {
+            std::fs::copy(entry.path(), path).unwrap();
+        }
+    }
+    target
+}
+
+/// This function creates a fresh temporary directory holding the given files, by path relative to
+/// it, for tests that need a site or an app other than `src/test/data/app`
+pub fn temp_site(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("fission-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    for (path, contents) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    root
+}
+
+/// An in memory stand-in for IPFS. Every entry gets a fake CID derived from its name, and
+/// everything that was called is recorded in `calls`.
+#[derive(Default)]
+pub struct StubIpfs {
+    pub calls: Mutex<Vec<String>>,
+    pub config: Mutex<KuboConfig>,
+    /// The directories `ls` can list, by CID
+    pub published: Mutex<HashMap<String, Vec<Link>>>,
+    /// The blocks `get_block` can return, by CID
+    pub blocks: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl StubIpfs {
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn fake_hashes(&self, path: &Path, filter: &FileFilter) -> HashMap<String, String> {
+        let prefix = path.parent().unwrap_or_else(|| Path::new(""));
+        filter
+            .walk(path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                let name = entry
+                    .path()
+                    .strip_prefix(prefix)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (name.clone(), format!("cid-of-{}", name))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Ipfs for StubIpfs {
+    async fn add(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>> {
+        self.record(format!("add {}", path.display()));
+        Ok(self.fake_hashes(path, filter))
+    }
+    async fn hash(&self, path: &Path, filter: &FileFilter) -> Result<HashMap<String, String>> {
+        self.record(format!("hash {}", path.display()));
+        Ok(self.fake_hashes(path, filter))
+    }
+    async fn ls(&self, cid: &str) -> Result<Vec<Link>> {
+        self.record(format!("ls {}", cid));
+        Ok(self
+            .published
+            .lock()
+            .unwrap()
+            .get(cid)
+            .cloned()
+            .unwrap_or_default())
+    }
+    async fn get_block(&self, cid: &str) -> Result<Vec<u8>> {
+        self.record(format!("block {}", cid));
+        match self.blocks.lock().unwrap().get(cid) {
+            Some(block) => Ok(block.clone()),
+            None => anyhow::bail!("Block {} not found", cid),
+        }
+    }
+    async fn connect_to(&self, peer_id: &str) -> Result<()> {
+        self.record(format!("connect {}", peer_id));
+        Ok(())
+    }
+    async fn disconnect(&self, peer_id: &str) -> Result<()> {
+        self.record(format!("disconnect {}", peer_id));
+        Ok(())
+    }
+    async fn get_connected(&self) -> Result<Vec<PeerInfo>> {
+        Ok(vec![])
+    }
+    async fn ping(&self, _peer_id: &str, count: u16) -> Result<Vec<Duration>> {
+        Ok(vec![Duration::from_millis(1); count as usize])
+    }
+    async fn id(&self) -> Result<NodeId> {
+        Ok(NodeId {
+            peer_id: "stub".to_string(),
+            addresses: vec![],
+            agent_version: "stub".to_string(),
+        })
+    }
+    async fn set_config(&self, _property: &str, _val: &Value) -> Result<()> {
+        Ok(())
+    }
+    async fn get_config(&self, _property: &str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+    async fn get_full_config(&self) -> Result<KuboConfig> {
+        Ok(self.config.lock().unwrap().clone())
+    }
+    async fn replace_config(&self, config: &KuboConfig) -> Result<()> {
+        *self.config.lock().unwrap() = config.clone();
+        Ok(())
+    }
+}
+
+/// A stand-in for the Fission server that records every call
+#[derive(Default)]
+pub struct StubServer {
+    pub calls: Mutex<Vec<String>>,
+    /// The CID of every app that was published, by URL
+    pub apps: Mutex<HashMap<String, String>>,
+    /// When each app was last published, by URL
+    pub published_at: Mutex<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl FissionServer for StubServer {
+    fn did(&self) -> &str {
+        "did:key:z6MkStub"
+    }
+
+    async fn create_app(&self, subdomain: Option<&str>) -> Result<String> {
+        let subdomain = subdomain.unwrap_or("generated-name");
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("create {}", subdomain));
+        Ok(format!("{}.fission.app", subdomain))
+    }
+
+    async fn list_apps(&self) -> Result<Vec<AppInfo>> {
+        let apps = self.apps.lock().unwrap();
+        let published_at = self.published_at.lock().unwrap();
+        Ok(apps
+            .iter()
+            .map(|(url, cid)| AppInfo {
+                urls: vec![url.clone()],
+                cid: Some(cid.clone()),
+                published_at: published_at.get(url).cloned(),
+                ..AppInfo::default()
+            })
+            .collect())
+    }
+
+    async fn destroy_app(&self, url: &str) -> Result<()> {
+        self.calls.lock().unwrap().push(format!("destroy {}", url));
+        self.apps.lock().unwrap().remove(url);
+        Ok(())
+    }
+
+    async fn update_app(&self, url: &str, cid: &str, copy_data: bool) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("update {} {} {}", url, cid, copy_data));
+        self.apps
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), cid.to_string());
+        Ok(())
+    }
+
+    async fn app_cid(&self, url: &str) -> Result<Option<String>> {
+        Ok(self.apps.lock().unwrap().get(url).cloned())
+    }
+
+    async fn app_info(&self, url: &str) -> Result<Option<AppInfo>> {
+        let published_at = self.published_at.lock().unwrap();
+        Ok(self.apps.lock().unwrap().get(url).map(|cid| AppInfo {
+            urls: vec![url.to_string()],
+            cid: Some(cid.clone()),
+            published_at: published_at.get(url).cloned(),
+            owner: Some("stub-user".to_string()),
+            ..AppInfo::default()
+        }))
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use crate::app::watch::{is_temp_file, Changes, WatchedPaths};
+use crate::test::stubs::temp_site;
+
+#[test]
+fn ignores_editor_temp_files() {
+    for name in [
+        "index.html~",
+        ".index.html.swp",
+        ".#index.html",
+        "#index.html#",
+        "4913",
+        "build/.DS_Store",
+    ] {
+        assert!(is_temp_file(Path::new(name)), "{}", name);
+    }
+    for name in ["index.html", "assets/style.css", "swp", "build/4913.js"] {
+        assert!(!is_temp_file(Path::new(name)), "{}", name);
+    }
+}
+
+#[test]
+fn rebuilds_on_source_changes() {
+    let app_dir = temp_site(
+        "watch-sources",
+        &[(".gitignore", "/dist\n*.log\n"), ("src/main.js", "")],
+    );
+    let build_dir = app_dir.join("dist");
+    let watched = WatchedPaths::new(build_dir.clone(), Some(app_dir.clone()));
+    assert_eq!(
+        watched.classify(&app_dir.join("src/main.js")),
+        Changes::Sources
+    );
+    assert_eq!(
+        watched.classify(&build_dir.join("index.html")),
+        Changes::Built
+    );
+    for ignored in [
+        "node_modules/.vite/deps/react.js",
+        ".git/index",
+        "build.log",
+        "src/.main.js.swp",
+    ] {
+        assert_eq!(
+            watched.classify(&app_dir.join(ignored)),
+            Changes::None,
+            "{}",
+            ignored
+        );
+    }
+
+    // Without a build command only the build directory matters
+    let watched = WatchedPaths::new(build_dir.clone(), None);
+    assert_eq!(
+        watched.classify(&app_dir.join("src/main.js")),
+        Changes::None
+    );
+    assert_eq!(
+        watched.classify(&build_dir.join("index.html")),
+        Changes::Built
+    );
+}
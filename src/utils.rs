@@ -1,2 +1,5 @@
+pub mod browser;
 pub mod config;
+pub mod dns;
+pub mod file_filter;
 pub mod file_management;
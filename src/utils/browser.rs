@@ -0,0 +1,18 @@
+use std::process::Command;
+
+use anyhow::Result;
+
+/// Opens a URL in the user's default browser
+pub fn open_in_browser(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url).spawn()?.wait()?;
+    Ok(())
+}
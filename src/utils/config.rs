@@ -3,5 +3,55 @@ pub const IPFS_RETRY_ATTEMPTS: u16 = 4;
 pub const IPFS_ADDR: &str = "127.0.0.1";
 pub const IPFS_EXE: &str = "ipfs";
 pub const HTTP_MULTIPART_BOUNDARY: &str = "------------------------I_am_a_boundary_123A123";
+pub const HTTP_BODY_CHUNK_SIZE: usize = 64 * 1024; // In bytes
 pub const IPFS_BOOT_TIME_OUT: u16 = 45; // In seconds
 pub const IPFS_SLEEP_LENGTH: u8 = 1; //In seconds
+pub const CONFIG_DIR: &str = ".config/fission";
+pub const CONFIG_FILE: &str = "config.yaml";
+pub const IPFS_API_ENV: &str = "FISSION_IPFS_API";
+pub const IPFS_TOKEN_ENV: &str = "FISSION_IPFS_TOKEN";
+pub const IPFS_CA_CERT_ENV: &str = "FISSION_IPFS_CA_CERT";
+pub const IPFS_PEERS_ENV: &str = "FISSION_IPFS_PEERS";
+pub const FISSION_CLUSTER_PEERS: &[&str] = &["/dns4/production-ipfs-cluster-us-east-1-node2.runfission.com/tcp/4003/wss/p2p/12D3KooWQ2hL9NschcJ1Suqa1TybJc2ZaacqoQMBT3ziFC7Ye2BZ"];
+pub const IPFS_BIN_ENV: &str = "FISSION_IPFS_BIN";
+pub const IPFS_SUPPORTED_VERSIONS: &str = ">=0.13.0, <1.0.0";
+pub const FISSION_API_URL: &str = "https://runfission.com";
+pub const FISSION_STAGING_API_URL: &str = "https://runfission.net";
+pub const FISSION_LOCAL_API_URL: &str = "http://localhost:1337";
+pub const FISSION_API_PATH: &str = "/v2/api";
+pub const UCAN_VERSION: &str = "0.7.0";
+pub const UCAN_LIFETIME: u64 = 300; // In seconds
+pub const APP_CONFIG_FILE: &str = "fission.yaml";
+pub const APP_CONFIG_TOML_FILE: &str = "fission.toml";
+pub const APP_CONFIG_VERSION: u32 = 2;
+pub const WATCH_DEBOUNCE_MS: u64 = 300; // In milliseconds
+pub const WATCH_POLL_MS: u64 = 200; // In milliseconds
+pub const IGNORE_FILE: &str = ".fissionignore";
+pub const DEFAULT_IGNORES: &[&str] = &[
+    ".git/",
+    "node_modules/",
+    ".env",
+    ".env.*",
+    "*.map",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+    "*~",
+    "*.swp",
+    IGNORE_FILE,
+    "/.fission/",
+    "!.well-known/",
+];
+pub const SCAN_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024; // In bytes
+pub const APP_STATE_DIR: &str = ".fission";
+pub const PUBLISH_STATE_FILE: &str = "publish.json";
+pub const HISTORY_FILE: &str = "history.json";
+pub const IPFS_GATEWAY_URL: &str = "https://ipfs.runfission.com";
+pub const DNS_RESOLVER_URL: &str = "https://cloudflare-dns.com/dns-query";
+pub const PREVIEW_TTL_DAYS: u64 = 7;
+pub const PREVIEW_INFIX: &str = "-preview-";
+pub const SERVE_PORT: u16 = 8888;
+pub const REDIRECTS_FILE: &str = "_redirects";
+pub const HEADERS_FILE: &str = "_headers";
+pub const STAGE_DIR: &str = "stage";
+pub const ASSET_MANIFEST_FILE: &str = "fission-assets.json";
@@ -0,0 +1,138 @@
+use anyhow::{bail, Context, Result};
+use hyper::client::HttpConnector;
+use hyper::header::ACCEPT;
+use hyper::{Body, Client, Request};
+use hyper_tls::HttpsConnector;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use tokio::runtime::Runtime;
+
+const TYPE_A: u16 = 1;
+const TYPE_CNAME: u16 = 5;
+const TYPE_TXT: u16 = 16;
+
+/// Looks up DNS records. Anything that can answer these questions will do, which lets the domain
+/// checks run against a stub.
+pub trait Resolver {
+    /// This method returns the TXT records at `name`, with the strings of each joined
+    fn txt(&self, name: &str) -> Result<Vec<String>>;
+    /// This method returns what `name` is a CNAME for, without the trailing dot
+    fn cname(&self, name: &str) -> Result<Option<String>>;
+    /// This method returns the IPv4 addresses `name` resolves to, following CNAMEs
+    fn a(&self, name: &str) -> Result<Vec<String>>;
+}
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    kind: u16,
+    data: String,
+}
+
+/// A resolver that asks a DNS over HTTPS server, using the JSON API Cloudflare and Google offer.
+/// Going over HTTPS sidesteps whatever the local resolver has cached.
+pub struct DohResolver {
+    url: String,
+    client: Client<HttpsConnector<HttpConnector>>,
+    tokio: Runtime,
+}
+
+impl DohResolver {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            client: Client::builder().build(HttpsConnector::new()),
+            tokio: Runtime::new()?,
+        })
+    }
+
+    /// Returns the data of every answer of the given type
+    fn query(&self, name: &str, kind: u16) -> Result<Vec<String>> {
+        let uri = format!(
+            "{}?name={}&type={}",
+            self.url,
+            utf8_percent_encode(name, NON_ALPHANUMERIC),
+            kind
+        );
+        let res: DohResponse = self.tokio.block_on(async {
+            let req = Request::get(&uri)
+                .header(ACCEPT, "application/dns-json")
+                .body(Body::empty())?;
+            let res = self
+                .client
+                .request(req)
+                .await
+                .with_context(|| format!("Failed to reach the DNS resolver at {}", self.url))?;
+            let status = res.status();
+            let bytes = hyper::body::to_bytes(res.into_body()).await?;
+            if !status.is_success() {
+                bail!("The DNS resolver failed to look up {} ({})", name, status);
+            }
+            serde_json::from_slice(&bytes)
+                .with_context(|| format!("The DNS resolver at {} returned invalid JSON", self.url))
+        })?;
+        // 3 is NXDOMAIN, which only means there is nothing there yet
+        if res.status != 0 && res.status != 3 {
+            bail!("Looking up {} failed with DNS status {}", name, res.status);
+        }
+        Ok(res
+            .answer
+            .into_iter()
+            .filter(|answer| answer.kind == kind)
+            .map(|answer| answer.data)
+            .collect())
+    }
+}
+
+impl Resolver for DohResolver {
+    fn txt(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .query(name, TYPE_TXT)?
+            .iter()
+            .map(|data| join_txt(data))
+            .collect())
+    }
+
+    fn cname(&self, name: &str) -> Result<Option<String>> {
+        Ok(self
+            .query(name, TYPE_CNAME)?
+            .into_iter()
+            .next()
+            .map(|target| target.trim_end_matches('.').to_string()))
+    }
+
+    fn a(&self, name: &str) -> Result<Vec<String>> {
+        self.query(name, TYPE_A)
+    }
+}
+
+/// TXT data comes as one or more quoted strings, such as `"dnslink=/ipns/" "my-app.fission.app"`
+pub fn join_txt(data: &str) -> String {
+    if !data.starts_with('"') {
+        return data.to_string();
+    }
+    let mut joined = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in data.chars() {
+        match c {
+            _ if escaped => {
+                joined.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if quoted => joined.push(c),
+            _ => {}
+        }
+    }
+    joined
+}
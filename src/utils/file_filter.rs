@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::utils::config::{DEFAULT_IGNORES, IGNORE_FILE};
+
+/// Decides which files under a directory get published. It follows gitignore syntax, with
+/// `.fissionignore` files in any directory taking priority over the patterns from the app config,
+/// which in turn take priority over the built-in defaults. Dot files are skipped unless
+/// whitelisted or `include_hidden` is set.
+#[derive(Clone, Debug, Default)]
+pub struct FileFilter {
+    /// Extra patterns, such as the `ignore` list of the app config
+    pub patterns: Vec<String>,
+    pub include_hidden: bool,
+    /// Keep every file, ignoring all of the rules above
+    pub disabled: bool,
+}
+
+impl FileFilter {
+    /// This method creates a filter with the built-in defaults and the given extra patterns
+    pub fn new(patterns: Vec<String>, include_hidden: bool) -> Self {
+        Self {
+            patterns,
+            include_hidden,
+            disabled: false,
+        }
+    }
+
+    /// This method creates a filter that keeps every file
+    pub fn none() -> Self {
+        Self {
+            disabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// This method walks `root` depth first, sorted by file name, returning every file and
+    /// directory that isn't filtered out. `root` itself is always included.
+    pub fn walk(&self, root: &Path) -> Result<Vec<DirEntry>> {
+        let base = if root.is_dir() {
+            root
+        } else {
+            root.parent().unwrap_or(root)
+        };
+        // Outermost first, so the last matcher that has an opinion wins
+        let mut matchers: Vec<(PathBuf, Gitignore)> = vec![];
+        if !self.disabled {
+            matchers.push((base.to_path_buf(), build_matcher(base, DEFAULT_IGNORES)?));
+            matchers.push((base.to_path_buf(), build_matcher(base, &self.patterns)?));
+        }
+
+        let mut entries = vec![];
+        let mut walker = WalkDir::new(root).sort_by_file_name().into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = entry?;
+            let is_dir = entry.file_type().is_dir();
+            if self.disabled {
+                entries.push(entry);
+                continue;
+            }
+
+            // Forget the ignore files of directories we have left
+            matchers.retain(|(dir, _)| entry.path().starts_with(dir));
+            if entry.depth() > 0 && self.is_ignored(&matchers, &entry) {
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            let ignore_file = entry.path().join(IGNORE_FILE);
+            if is_dir && ignore_file.is_file() {
+                let (matcher, err) = Gitignore::new(&ignore_file);
+                if let Some(err) = err {
+                    return Err(err).with_context(|| format!("Failed to read {:?}", ignore_file));
+                }
+                matchers.push((entry.path().to_path_buf(), matcher));
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    fn is_ignored(&self, matchers: &[(PathBuf, Gitignore)], entry: &DirEntry) -> bool {
+        let is_dir = entry.file_type().is_dir();
+        for (_, matcher) in matchers.iter().rev() {
+            match matcher.matched(entry.path(), is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        !self.include_hidden && entry.file_name().to_string_lossy().starts_with('.')
+    }
+}
+
+/// Builds a matcher for gitignore style patterns relative to `root`
+pub fn build_matcher<S: AsRef<str>>(root: &Path, patterns: &[S]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern.as_ref())
+            .with_context(|| format!("Invalid ignore pattern {:?}", pattern.as_ref()))?;
+    }
+    Ok(builder.build()?)
+}
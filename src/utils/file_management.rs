@@ -1,20 +1,22 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use anyhow::{bail, Result};
 use colored::Colorize;
-use walkdir::WalkDir;
 
-pub fn get_files_in(dir: &str) -> Result<HashMap<String, Vec<u8>>> {
+use crate::utils::file_filter::FileFilter;
+
+pub fn get_files_in(dir: &str, filter: &FileFilter) -> Result<HashMap<String, Vec<u8>>> {
     let mut files = HashMap::new();
-    for entry_result in WalkDir::new(dir) {
-        let entry = match entry_result {
-            Ok(x) => x,
-            Err(e) => bail!(
-                "{}\n{}",
-                "failed to get item in directory, failed with error:".red(),
-                e
-            ),
-        };
+    let entries = match filter.walk(Path::new(dir)) {
+        Ok(x) => x,
+        Err(e) => bail!(
+            "{}\n{:#}",
+            "failed to get item in directory, failed with error:".red(),
+            e
+        ),
+    };
+    for entry in entries {
         if entry.path().is_file() {
             let file_data = match std::fs::read(entry.path()) {
                 Ok(x) => x,
@@ -34,3 +36,18 @@ pub fn get_files_in(dir: &str) -> Result<HashMap<String, Vec<u8>>> {
     }
     return anyhow::Ok(files);
 }
+
+/// Formats a number of bytes for humans, such as `1.5 KiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}